@@ -9,6 +9,7 @@ use ratatui::backend::CrosstermBackend;
 use ratatui::crossterm::event::{self, Event as CrosstermEvent, KeyCode, KeyEvent, KeyEventKind};
 use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 
+use crate::demangle::Demangle;
 use crate::error::Result;
 use eprofiler_proto::opentelemetry::proto::collector::profiles::v1development::ExportProfilesServiceRequest;
 
@@ -61,6 +62,7 @@ pub(crate) struct DebugState {
     pub running: bool,
     pub listen_addr: String,
     pub search: Search,
+    pub demangle: Demangle,
 }
 
 impl DebugState {
@@ -125,6 +127,12 @@ impl DebugState {
                 self.navigate(self.requests.len() - 1);
             }
             KeyCode::Char('g') if !self.requests.is_empty() => self.navigate(0),
+            KeyCode::Char('M') => {
+                self.demangle = self.demangle.next();
+                if !self.search.pattern.is_empty() {
+                    self.recompute_hits();
+                }
+            }
             KeyCode::Char('d') | KeyCode::PageDown => {
                 self.scroll_y = self.scroll_y.saturating_add(20);
             }
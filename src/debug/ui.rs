@@ -12,6 +12,7 @@ use eprofiler_proto::opentelemetry::proto::common::v1 as common;
 use eprofiler_proto::opentelemetry::proto::profiles::v1development as profiles;
 
 use super::DebugState;
+use crate::demangle::{self, Demangle};
 
 const BG: Color = Color::Rgb(16, 16, 22);
 const ACCENT: Color = Color::Rgb(59, 130, 246);
@@ -130,7 +131,7 @@ fn draw_border(buf: &mut Buffer, r: Rect, title: &str, color: Color) {
     }
 }
 
-struct Dict<'a>(&'a profiles::ProfilesDictionary);
+struct Dict<'a>(&'a profiles::ProfilesDictionary, Demangle);
 
 impl Dict<'_> {
     fn str(&self, idx: i32) -> String {
@@ -195,11 +196,15 @@ impl Dict<'_> {
             .function_table
             .get(line.function_index as usize)
             .filter(|_| line.function_index > 0)
-            .map(|f| self.str(f.name_strindex))
+            .map(|f| self.symbol(f.name_strindex))
             .filter(|n| !n.is_empty())
             .unwrap_or_else(|| "[unknown]".into())
     }
 
+    fn symbol(&self, idx: i32) -> String {
+        demangle::symbol(&self.str(idx), self.1).into_owned()
+    }
+
     fn value_type(&self, vt: &profiles::ValueType) -> String {
         format!(
             "{} / {}",
@@ -239,9 +244,9 @@ impl Doc {
         self.0.is_empty()
     }
 
-    fn from_request(req: &ExportProfilesServiceRequest) -> Self {
+    fn from_request(req: &ExportProfilesServiceRequest, mode: Demangle) -> Self {
         let mut doc = Self::new();
-        let dict = req.dictionary.as_ref().map(Dict);
+        let dict = req.dictionary.as_ref().map(|d| Dict(d, mode));
         if let Some(ref d) = dict {
             doc.dictionary(d);
         }
@@ -384,7 +389,7 @@ impl Doc {
             for (i, f) in p.function_table.iter().enumerate().skip(1) {
                 let mut spans = vec![
                     dim(&format!("  [{i:>3}] ")),
-                    d.str(f.name_strindex).fg(BRIGHT),
+                    d.symbol(f.name_strindex).fg(BRIGHT),
                 ];
                 let sys = d.str(f.system_name_strindex);
                 if !sys.is_empty() {
@@ -603,7 +608,7 @@ impl DebugState {
             .filter(|_| !self.search.pattern.is_empty())
             .map(|req| {
                 let pat = self.search.pattern.to_lowercase();
-                let lines: Vec<Line> = Doc::from_request(req).into();
+                let lines: Vec<Line> = Doc::from_request(req, self.demangle).into();
                 lines
                     .iter()
                     .enumerate()
@@ -667,8 +672,10 @@ impl DebugState {
                 self.listen_addr.clone().fg(Color::Rgb(130, 130, 150)),
                 sep.clone(),
                 format!("Request {cur} of {total}").fg(BRIGHT).bold(),
-                sep,
+                sep.clone(),
                 format!("{total} queued").fg(Color::Rgb(110, 110, 130)),
+                sep,
+                format!("symbols: {}", self.demangle.label()).fg(Color::Rgb(110, 110, 130)),
             ])),
             area,
         );
@@ -678,7 +685,7 @@ impl DebugState {
         let Some(req) = self.requests.get(self.current) else {
             return;
         };
-        let lines: Vec<Line> = Doc::from_request(req).into();
+        let lines: Vec<Line> = Doc::from_request(req, self.demangle).into();
         self.scroll_y = self
             .scroll_y
             .min(lines.len().saturating_sub(area.height as usize));
@@ -745,6 +752,7 @@ impl DebugState {
                 ("[j/k]", " scroll "),
                 ("[d/u]", " page "),
                 ("[/]", " search "),
+                ("[M]", " demangle "),
             ];
             h.extend([("[g/G]", " first/last "), ("[q]", " quit ")]);
             h
@@ -0,0 +1,115 @@
+use std::borrow::Cow;
+
+use cpp_demangle::DemangleOptions;
+
+/// How native symbols are displayed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Demangle {
+    Mangled,
+    #[default]
+    Full,
+    /// Without parameters, return types and Rust hashes.
+    Short,
+}
+
+impl Demangle {
+    pub fn next(self) -> Self {
+        match self {
+            Demangle::Mangled => Demangle::Full,
+            Demangle::Full => Demangle::Short,
+            Demangle::Short => Demangle::Mangled,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Demangle::Mangled => "mangled",
+            Demangle::Full => "demangled",
+            Demangle::Short => "short",
+        }
+    }
+}
+
+/// Demangles an Itanium C++ or Rust (legacy or v0) symbol, returning it
+/// unchanged if it is not mangled.
+pub fn symbol(sym: &str, mode: Demangle) -> Cow<'_, str> {
+    if mode == Demangle::Mangled || !is_mangled(sym) {
+        return Cow::Borrowed(sym);
+    }
+    if let Ok(demangled) = rustc_demangle::try_demangle(sym) {
+        return Cow::Owned(match mode {
+            Demangle::Short => format!("{demangled:#}"),
+            _ => demangled.to_string(),
+        });
+    }
+    // Mach-O symbols carry an extra leading underscore.
+    let itanium = sym
+        .strip_prefix('_')
+        .filter(|s| s.starts_with("_Z"))
+        .unwrap_or(sym);
+    let options = match mode {
+        Demangle::Short => DemangleOptions::new().no_params().no_return_type(),
+        _ => DemangleOptions::new(),
+    };
+    cpp_demangle::Symbol::new(itanium)
+        .ok()
+        .and_then(|s| s.demangle(&options).ok())
+        .map_or(Cow::Borrowed(sym), Cow::Owned)
+}
+
+/// Demangles every symbol in a frame label such as
+/// `_ZN3foo3barEv [Native] / _ZN3foo3bazEv [Native] [Inline]`.
+pub fn label(name: &str, mode: Demangle) -> Cow<'_, str> {
+    if mode == Demangle::Mangled || !name.split(' ').any(is_mangled) {
+        return Cow::Borrowed(name);
+    }
+    let parts: Vec<Cow<str>> = name.split(' ').map(|part| symbol(part, mode)).collect();
+    Cow::Owned(parts.join(" "))
+}
+
+fn is_mangled(sym: &str) -> bool {
+    sym.starts_with("_Z") || sym.starts_with("__Z") || sym.starts_with("_R")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangles_cpp_and_rust() {
+        let cpp = "_ZN3foo3barEi";
+        assert_eq!(symbol(cpp, Demangle::Full), "foo::bar(int)");
+        assert_eq!(symbol(cpp, Demangle::Short), "foo::bar");
+        assert_eq!(symbol(cpp, Demangle::Mangled), cpp);
+        assert_eq!(symbol("__ZN3foo3barEi", Demangle::Short), "foo::bar");
+
+        let legacy = "_ZN4core3ptr13drop_in_place17h1a2b3c4d5e6f7a8bE";
+        assert_eq!(
+            symbol(legacy, Demangle::Full),
+            "core::ptr::drop_in_place::h1a2b3c4d5e6f7a8b"
+        );
+        assert_eq!(symbol(legacy, Demangle::Short), "core::ptr::drop_in_place");
+        assert_eq!(
+            symbol("_RNvCs1234_7mycrate3foo", Demangle::Short),
+            "mycrate::foo"
+        );
+
+        assert_eq!(symbol("main", Demangle::Full), "main");
+        assert_eq!(symbol("_ZN3foo", Demangle::Full), "_ZN3foo");
+    }
+
+    #[test]
+    fn demangles_every_symbol_of_a_label() {
+        assert_eq!(
+            label(
+                "_ZN3foo3barEi [Native] / _ZN3foo3bazEv [Native] [Inline]",
+                Demangle::Short
+            ),
+            "foo::bar [Native] / foo::baz [Native] [Inline]"
+        );
+        assert!(matches!(
+            label("main [Native]", Demangle::Full),
+            Cow::Borrowed(_)
+        ));
+    }
+}
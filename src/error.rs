@@ -22,4 +22,12 @@ pub enum Error {
     Storage(#[from] fjall::Error),
     #[error("incompatible storage format at `{}`: delete the directory and restart", .0.display())]
     StorageVersionMismatch(PathBuf),
+    #[error("invalid normalization rule at {}:{line}: {message}", path.display())]
+    Rules {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    #[error("invalid trigger `{spec}`: {message}")]
+    Trigger { spec: String, message: String },
 }
@@ -1,22 +1,194 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+
+use regex::Regex;
+
+/// Name of the leaf that collects the samples of pruned subtrees.
+pub const OTHER_FRAME: &str = "[other]";
+
+/// Runtime a frame belongs to, from the `profile.frame.type` attribute.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum FrameType {
+    #[default]
+    Unknown,
+    Native,
+    Kernel,
+    Jvm,
+    Python,
+    Php,
+    Ruby,
+    Perl,
+    Js,
+    DotNet,
+    Beam,
+    Go,
+}
+
+impl FrameType {
+    /// In discriminant order, so that `ALL[t as usize] == t`.
+    pub const ALL: [FrameType; 12] = [
+        FrameType::Unknown,
+        FrameType::Native,
+        FrameType::Kernel,
+        FrameType::Jvm,
+        FrameType::Python,
+        FrameType::Php,
+        FrameType::Ruby,
+        FrameType::Perl,
+        FrameType::Js,
+        FrameType::DotNet,
+        FrameType::Beam,
+        FrameType::Go,
+    ];
+
+    pub fn from_attribute(value: &str) -> Option<Self> {
+        Some(match value {
+            "native" => FrameType::Native,
+            "kernel" => FrameType::Kernel,
+            "jvm" => FrameType::Jvm,
+            "cpython" => FrameType::Python,
+            "php" | "phpjit" => FrameType::Php,
+            "ruby" => FrameType::Ruby,
+            "perl" => FrameType::Perl,
+            "v8js" => FrameType::Js,
+            "dotnet" => FrameType::DotNet,
+            "beam" => FrameType::Beam,
+            "go" => FrameType::Go,
+            _ => return None,
+        })
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FrameType::Unknown => "Unknown",
+            FrameType::Native => "Native",
+            FrameType::Kernel => "Kernel",
+            FrameType::Jvm => "JVM",
+            FrameType::Python => "Python",
+            FrameType::Php => "PHP",
+            FrameType::Ruby => "Ruby",
+            FrameType::Perl => "Perl",
+            FrameType::Js => "JS",
+            FrameType::DotNet => ".NET",
+            FrameType::Beam => "Beam",
+            FrameType::Go => "Go",
+        }
+    }
+}
+
+/// One element of a stack passed to [`FlameNode::add_stack`].
+pub trait StackFrame {
+    fn name(&self) -> &str;
+
+    fn frame_type(&self) -> FrameType {
+        FrameType::Unknown
+    }
+
+    fn module(&self) -> &str {
+        ""
+    }
+}
+
+impl StackFrame for String {
+    fn name(&self) -> &str {
+        self
+    }
+}
+
+impl StackFrame for &FlameNode {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn frame_type(&self) -> FrameType {
+        self.frame_type
+    }
+
+    fn module(&self) -> &str {
+        &self.module
+    }
+}
+
+/// A resolved frame label together with its type and module.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub name: String,
+    pub frame_type: FrameType,
+    pub module: String,
+}
+
+/// A resolved stack, thread first, shared by every sample that recorded it.
+pub type Stack = Arc<[Frame]>;
+
+/// Stack of frames of unknown type and module, thread first.
+#[cfg(test)]
+pub(crate) fn test_stack(names: &[&str]) -> Stack {
+    names
+        .iter()
+        .map(|name| Frame {
+            name: name.to_string(),
+            frame_type: FrameType::Unknown,
+            module: String::new(),
+        })
+        .collect()
+}
+
+/// Timestamped samples of one resource, by thread, as timestamp, weight
+/// and stack. The weight is 1 for timestamps carried by a sample, and a
+/// share of its value for spread ones.
+#[derive(Default)]
+pub struct ResourceSamples {
+    pub threads: HashMap<String, Vec<(u64, u64, Stack)>>,
+    /// Some timestamps were spread over the profile's interval because the
+    /// samples carried none.
+    pub approximated: bool,
+}
+
+/// Timestamped samples by resource. Resources are kept apart because their
+/// agents' clocks need not agree.
+pub type Timestamps = HashMap<String, ResourceSamples>;
+
+impl StackFrame for Frame {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn frame_type(&self) -> FrameType {
+        self.frame_type
+    }
+
+    fn module(&self) -> &str {
+        &self.module
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct FlameNode {
     pub name: String,
+    pub frame_type: FrameType,
+    /// Basename of the executable or library mapping the code; empty when
+    /// unknown.
+    pub module: String,
     pub total_value: i64,
     pub self_value: i64,
     pub children: Vec<FlameNode>,
     child_index: HashMap<String, usize>,
+    /// Most recursive calls folded into this frame by
+    /// [`FlameGraph::collapse_recursion`] on any one stack.
+    pub folded_depth: usize,
 }
 
 impl FlameNode {
     pub fn new(name: String) -> Self {
         Self {
             name,
+            frame_type: FrameType::Unknown,
+            module: String::new(),
             total_value: 0,
             self_value: 0,
             children: Vec::new(),
             child_index: HashMap::new(),
+            folded_depth: 0,
         }
     }
 
@@ -30,35 +202,110 @@ impl FlameNode {
         self.child_index.get(name).copied()
     }
 
-    pub fn add_stack(&mut self, stack: &[String], value: i64) {
+    pub fn add_stack<F: StackFrame>(&mut self, stack: &[F], value: i64) {
         self.total_value += value;
-        if stack.is_empty() {
+        let Some((frame, rest)) = stack.split_first() else {
             self.self_value += value;
             return;
-        }
-        let idx = if let Some(&idx) = self.child_index.get(&stack[0]) {
+        };
+        let idx = if let Some(&idx) = self.child_index.get(frame.name()) {
             idx
         } else {
             let idx = self.children.len();
-            self.children.push(FlameNode::new(stack[0].clone()));
-            self.child_index.insert(stack[0].clone(), idx);
+            let mut child = FlameNode::new(frame.name().to_string());
+            child.frame_type = frame.frame_type();
+            child.module = frame.module().to_string();
+            self.children.push(child);
+            self.child_index.insert(frame.name().to_string(), idx);
             idx
         };
-        self.children[idx].add_stack(&stack[1..], value);
+        self.children[idx].add_stack(rest, value);
     }
 
-    pub fn merge(&mut self, other: FlameNode) {
+    /// Adds the samples of `other` to this subtree, returning the number of
+    /// nodes created.
+    pub fn merge(&mut self, other: FlameNode) -> usize {
         self.total_value += other.total_value;
         self.self_value += other.self_value;
+        self.folded_depth = self.folded_depth.max(other.folded_depth);
+        let mut added = 0;
         for other_child in other.children {
             if let Some(&idx) = self.child_index.get(&other_child.name) {
-                self.children[idx].merge(other_child);
+                added += self.children[idx].merge(other_child);
             } else {
+                added += other_child.node_count();
                 let idx = self.children.len();
                 self.child_index.insert(other_child.name.clone(), idx);
                 self.children.push(other_child);
             }
         }
+        added
+    }
+
+    /// Removes the samples of `other` from this subtree, dropping children
+    /// that end up empty. Returns the number of nodes dropped.
+    pub fn subtract(&mut self, other: &FlameNode) -> usize {
+        self.total_value -= other.total_value;
+        self.self_value -= other.self_value;
+        let mut removed = 0;
+        for other_child in &other.children {
+            if let Some(&idx) = self.child_index.get(&other_child.name) {
+                removed += self.children[idx].subtract(other_child);
+            } else if let Some(&idx) = self.child_index.get(OTHER_FRAME) {
+                // The subtree was pruned, its samples live in `[other]`.
+                let folded = &mut self.children[idx];
+                folded.total_value -= other_child.total_value;
+                folded.self_value -= other_child.total_value;
+            }
+        }
+        let before = self.children.len();
+        self.children.retain(|c| {
+            let keep = c.total_value > 0;
+            if !keep {
+                removed += c.node_count();
+            }
+            keep
+        });
+        if self.children.len() != before {
+            self.rebuild_index();
+        }
+        removed
+    }
+
+    /// Folds every subtree missing from `shape` into an `[other]` leaf of
+    /// its parent, as pruning `shape` did. Subtracting the result from
+    /// `shape` then takes folded samples out of `[other]`.
+    pub fn fold_to(&mut self, shape: &FlameNode) {
+        let mut folded = 0;
+        let before = self.children.len();
+        self.children.retain_mut(|child| {
+            if child.name == OTHER_FRAME {
+                return true;
+            }
+            match shape.child_by_name(&child.name) {
+                Some(shape) => {
+                    child.fold_to(shape);
+                    true
+                }
+                None => {
+                    folded += child.total_value;
+                    false
+                }
+            }
+        });
+        if self.children.len() == before {
+            return;
+        }
+        if let Some(other) = self.children.iter_mut().find(|c| c.name == OTHER_FRAME) {
+            other.total_value += folded;
+            other.self_value += folded;
+        } else {
+            let mut other = FlameNode::new(OTHER_FRAME.to_string());
+            other.total_value = folded;
+            other.self_value = folded;
+            self.children.push(other);
+        }
+        self.rebuild_index();
     }
 
     pub fn sort_recursive(&mut self) {
@@ -84,6 +331,13 @@ impl FlameNode {
             .fold(self, |node, name| node.child_by_name(name).unwrap_or(node))
     }
 
+    /// Walk down child names, returning `None` if any name is missing.
+    pub fn find_path(&self, names: &[String]) -> Option<&FlameNode> {
+        names
+            .iter()
+            .try_fold(self, |node, name| node.child_by_name(name))
+    }
+
     /// Walk down child indices, stopping if an index is out of bounds.
     pub fn follow_indices(&self, indices: &[usize]) -> &FlameNode {
         indices
@@ -91,7 +345,172 @@ impl FlameNode {
             .fold(self, |node, &idx| node.children.get(idx).unwrap_or(node))
     }
 
-    #[allow(dead_code)]
+    /// Visit every stack recorded in this subtree with the value that ends
+    /// exactly at its last frame. The path excludes `self`.
+    pub fn for_each_stack<'a>(&'a self, f: &mut impl FnMut(&[&'a FlameNode], i64)) {
+        let mut path = Vec::new();
+        self.walk_stacks(&mut path, f);
+    }
+
+    fn walk_stacks<'a>(
+        &'a self,
+        path: &mut Vec<&'a FlameNode>,
+        f: &mut impl FnMut(&[&'a FlameNode], i64),
+    ) {
+        if self.self_value != 0 {
+            f(path, self.self_value);
+        }
+        for child in &self.children {
+            path.push(child);
+            child.walk_stacks(path, f);
+            path.pop();
+        }
+    }
+
+    /// Index paths (relative to `self`, in pre-order) of every descendant
+    /// whose name satisfies `pred`, and the value they cover. Matches nested
+    /// under another match count towards the value only once.
+    pub fn find_matches(&self, pred: impl Fn(&str) -> bool) -> (Vec<Vec<usize>>, i64) {
+        let mut matches = Vec::new();
+        let mut value = 0;
+        let mut path = Vec::new();
+        self.collect_matches(&pred, false, &mut path, &mut matches, &mut value);
+        (matches, value)
+    }
+
+    fn collect_matches(
+        &self,
+        pred: &impl Fn(&str) -> bool,
+        covered: bool,
+        path: &mut Vec<usize>,
+        matches: &mut Vec<Vec<usize>>,
+        value: &mut i64,
+    ) {
+        for (i, child) in self.children.iter().enumerate() {
+            path.push(i);
+            let hit = pred(&child.name);
+            if hit {
+                matches.push(path.clone());
+                if !covered {
+                    *value += child.total_value;
+                }
+            }
+            child.collect_matches(pred, covered || hit, path, matches, value);
+            path.pop();
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(FlameNode::node_count)
+            .sum::<usize>()
+    }
+
+    fn collect_totals(&self, out: &mut Vec<i64>) {
+        for child in &self.children {
+            out.push(child.total_value);
+            child.collect_totals(out);
+        }
+    }
+
+    /// Number of descendants left if every child with a total of at most
+    /// `threshold` were folded into `[other]`.
+    fn count_after_prune(&self, threshold: i64) -> usize {
+        let mut count = 0;
+        let mut folds = false;
+        for child in &self.children {
+            if child.total_value > threshold && child.name != OTHER_FRAME {
+                count += 1 + child.count_after_prune(threshold);
+            } else {
+                folds = true;
+            }
+        }
+        count + usize::from(folds)
+    }
+
+    /// Folds every descendant with a total of at most `threshold` into an
+    /// `[other]` leaf of its parent. Returns the number of nodes removed and
+    /// the value newly moved into `[other]`.
+    fn prune(&mut self, threshold: i64) -> (usize, i64) {
+        let mut removed = 0;
+        let mut folded = 0;
+        let mut other_value = 0;
+        let mut had_other = false;
+        let mut folds = false;
+        let mut kept = Vec::with_capacity(self.children.len());
+        for mut child in std::mem::take(&mut self.children) {
+            if child.name == OTHER_FRAME {
+                had_other = true;
+                other_value += child.total_value;
+            } else if child.total_value <= threshold {
+                folds = true;
+                removed += child.node_count();
+                folded += child.total_value;
+                other_value += child.total_value;
+            } else {
+                let (r, f) = child.prune(threshold);
+                removed += r;
+                folded += f;
+                kept.push(child);
+            }
+        }
+        if had_other || folds {
+            let mut other = FlameNode::new(OTHER_FRAME.to_string());
+            other.total_value = other_value;
+            other.self_value = other_value;
+            kept.push(other);
+            if !had_other {
+                removed -= 1;
+            }
+        }
+        self.children = kept;
+        self.rebuild_index();
+        (removed, folded)
+    }
+
+    /// Serializes the subtree in pre-order: name, frame type, module, self
+    /// value and child count per node. Totals are recomputed on decode.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&(self.name.len() as u32).to_be_bytes());
+        buf.extend_from_slice(self.name.as_bytes());
+        buf.push(self.frame_type as u8);
+        buf.extend_from_slice(&(self.module.len() as u32).to_be_bytes());
+        buf.extend_from_slice(self.module.as_bytes());
+        buf.extend_from_slice(&self.self_value.to_be_bytes());
+        buf.extend_from_slice(&(self.children.len() as u32).to_be_bytes());
+        for child in &self.children {
+            child.encode_into(buf);
+        }
+    }
+
+    /// Inverse of [`FlameNode::encode_into`], advancing `bytes` past the
+    /// decoded subtree. Older snapshot `format`s lack the frame type (1)
+    /// or the module (1 and 2).
+    pub fn decode(bytes: &mut &[u8], format: u8) -> Option<FlameNode> {
+        let name_len = take_u32(bytes)? as usize;
+        let name = String::from_utf8_lossy(take(bytes, name_len)?).into_owned();
+        let mut node = FlameNode::new(name);
+        if format >= 2 {
+            node.frame_type = *FrameType::ALL.get(take(bytes, 1)?[0] as usize)?;
+        }
+        if format >= 3 {
+            let module_len = take_u32(bytes)? as usize;
+            node.module = String::from_utf8_lossy(take(bytes, module_len)?).into_owned();
+        }
+        node.self_value = i64::from_be_bytes(take(bytes, 8)?.try_into().ok()?);
+        node.total_value = node.self_value;
+        for _ in 0..take_u32(bytes)? {
+            let child = FlameNode::decode(bytes, format)?;
+            node.total_value += child.total_value;
+            node.child_index
+                .insert(child.name.clone(), node.children.len());
+            node.children.push(child);
+        }
+        Some(node)
+    }
+
     pub fn max_depth(&self) -> usize {
         self.children
             .iter()
@@ -101,6 +520,44 @@ impl FlameNode {
     }
 }
 
+pub(crate) fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if bytes.len() < n {
+        return None;
+    }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Some(head)
+}
+
+pub(crate) fn take_u32(bytes: &mut &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(take(bytes, 4)?.try_into().ok()?))
+}
+
+fn collect_functions<'a>(
+    node: &'a FlameNode,
+    stats: &mut HashMap<&'a str, FunctionStats>,
+    on_stack: &mut HashMap<&'a str, usize>,
+) {
+    let entry = stats.entry(&node.name).or_insert_with(|| FunctionStats {
+        name: node.name.clone(),
+        frame_type: node.frame_type,
+        self_value: 0,
+        total_value: 0,
+    });
+    entry.self_value += node.self_value;
+    let depth = on_stack.entry(&node.name).or_default();
+    if *depth == 0 {
+        entry.total_value += node.total_value;
+    }
+    *depth += 1;
+    for child in &node.children {
+        collect_functions(child, stats, on_stack);
+    }
+    if let Some(depth) = on_stack.get_mut(node.name.as_str()) {
+        *depth -= 1;
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct FlameGraph {
     pub root: FlameNode,
@@ -113,9 +570,328 @@ impl FlameGraph {
         }
     }
 
-    pub fn add_stack(&mut self, stack: &[String], value: i64) {
+    pub fn add_stack<F: StackFrame>(&mut self, stack: &[F], value: i64) {
         self.root.add_stack(stack, value);
     }
+
+    /// Folds the smallest subtrees into `[other]` leaves once the tree
+    /// exceeds `max_nodes`, leaving headroom so the next merges do not
+    /// prune again immediately. Totals are preserved at every level.
+    /// Returns the number of nodes removed and the value folded.
+    pub fn prune_to(&mut self, max_nodes: usize) -> (usize, i64) {
+        if self.root.node_count() <= max_nodes {
+            return (0, 0);
+        }
+        let target = max_nodes - max_nodes / 10;
+        let mut totals = Vec::new();
+        self.root.collect_totals(&mut totals);
+        totals.sort_unstable();
+        totals.dedup();
+        // Fewer nodes survive as the threshold grows.
+        let idx = totals.partition_point(|&t| self.root.count_after_prune(t) + 1 > target);
+        let threshold = totals[idx.min(totals.len() - 1)];
+        let pruned = self.root.prune(threshold);
+        self.root.sort_recursive();
+        pruned
+    }
+
+    /// Aggregates every frame below the thread level by name.
+    pub fn functions(&self) -> Vec<FunctionStats> {
+        let mut stats: HashMap<&str, FunctionStats> = HashMap::new();
+        let mut on_stack: HashMap<&str, usize> = HashMap::new();
+        for thread in &self.root.children {
+            for child in &thread.children {
+                collect_functions(child, &mut stats, &mut on_stack);
+            }
+        }
+        stats.into_values().collect()
+    }
+
+    /// Bottom-up view: below each thread, leaf frames come first and their
+    /// callers follow. Frames keep the recursion folded into them.
+    pub fn inverted(&self) -> FlameGraph {
+        let mut inverted = FlameGraph::new();
+        let mut stack = Vec::new();
+        self.root.for_each_stack(&mut |path, value| {
+            stack.clear();
+            if let Some((thread, frames)) = path.split_first() {
+                stack.push(*thread);
+                stack.extend(frames.iter().rev());
+            }
+            inverted.add_stack(&stack, value);
+            let mut node = &mut inverted.root;
+            for frame in &stack {
+                let idx = node.child_index[&frame.name];
+                node = &mut node.children[idx];
+                node.folded_depth = node.folded_depth.max(frame.folded_depth);
+            }
+        });
+        inverted.root.sort_recursive();
+        inverted
+    }
+
+    /// Frame types present below the thread level, in [`FrameType::ALL`]
+    /// order.
+    pub fn frame_types(&self) -> Vec<FrameType> {
+        fn collect(node: &FlameNode, seen: &mut [bool; FrameType::ALL.len()]) {
+            seen[node.frame_type as usize] = true;
+            for child in &node.children {
+                collect(child, seen);
+            }
+        }
+        let mut seen = [false; FrameType::ALL.len()];
+        for thread in &self.root.children {
+            for child in &thread.children {
+                collect(child, &mut seen);
+            }
+        }
+        FrameType::ALL
+            .into_iter()
+            .filter(|&t| seen[t as usize])
+            .collect()
+    }
+
+    /// Folds directly recursive frames (`a → a`) into one. With `cycles`,
+    /// any frame already on the stack also folds everything above its
+    /// earlier occurrence (`a → b → a` becomes `a`).
+    pub fn collapse_recursion(&self, cycles: bool) -> FlameGraph {
+        let mut collapsed = FlameGraph::new();
+        let mut stack: Vec<&FlameNode> = Vec::new();
+        let mut folded: Vec<usize> = Vec::new();
+        self.root.for_each_stack(&mut |path, value| {
+            let Some((thread, frames)) = path.split_first() else {
+                return;
+            };
+            stack.clear();
+            folded.clear();
+            stack.push(thread);
+            folded.push(0);
+            for frame in frames {
+                let earlier = if cycles {
+                    stack[1..]
+                        .iter()
+                        .position(|n| n.name == frame.name)
+                        .map(|p| p + 1)
+                } else {
+                    (stack.len() > 1 && stack[stack.len() - 1].name == frame.name)
+                        .then(|| stack.len() - 1)
+                };
+                match earlier {
+                    Some(p) => {
+                        stack.truncate(p + 1);
+                        folded.truncate(p + 1);
+                        folded[p] += 1;
+                    }
+                    None => {
+                        stack.push(frame);
+                        folded.push(0);
+                    }
+                }
+            }
+            collapsed.add_stack(&stack, value);
+            let mut node = &mut collapsed.root;
+            for (frame, &depth) in stack.iter().zip(&folded) {
+                let idx = node.child_index[&frame.name];
+                node = &mut node.children[idx];
+                node.folded_depth = node.folded_depth.max(depth);
+            }
+        });
+        collapsed.root.sort_recursive();
+        collapsed
+    }
+
+    /// Splits every stack containing `name` at its outermost occurrence, so
+    /// recursive calls show up among the callees.
+    pub fn butterfly(&self, name: &str) -> Butterfly {
+        let mut callers = FlameNode::new(name.to_string());
+        let mut callees = FlameNode::new(name.to_string());
+        let mut stack: Vec<&FlameNode> = Vec::new();
+        self.root.for_each_stack(&mut |path, value| {
+            // The first level holds thread names.
+            let Some(pos) = path.iter().skip(1).position(|n| n.name == name) else {
+                return;
+            };
+            let pos = pos + 1;
+            callers.frame_type = path[pos].frame_type;
+            callees.frame_type = path[pos].frame_type;
+            stack.clear();
+            stack.extend(path[..pos].iter().rev());
+            callers.add_stack(&stack, value);
+            callees.add_stack(&path[pos + 1..], value);
+        });
+        callers.sort_recursive();
+        callees.sort_recursive();
+        Butterfly {
+            name: name.to_string(),
+            callers,
+            callees,
+        }
+    }
+
+    /// Rebuilds the graph through the enabled filters. Every focus filter
+    /// must match some frame of a stack for it to be kept; a single ignore
+    /// match drops it.
+    pub fn filtered(&self, filters: &[StackFilter]) -> FlameGraph {
+        let focus: Vec<&StackFilter> = filters
+            .iter()
+            .filter(|f| f.enabled && f.kind == FilterKind::Focus)
+            .collect();
+        let any = |frames: &[&FlameNode], kind| {
+            frames
+                .iter()
+                .any(|n| filters.iter().any(|f| f.matches(kind, n)))
+        };
+
+        let mut filtered = FlameGraph::new();
+        let mut stack = Vec::new();
+        self.root.for_each_stack(&mut |path, value| {
+            let Some((thread, frames)) = path.split_first() else {
+                return;
+            };
+            if any(frames, FilterKind::Ignore)
+                || !focus
+                    .iter()
+                    .all(|f| frames.iter().any(|n| f.matcher.is_match(n)))
+            {
+                return;
+            }
+            stack.clear();
+            stack.push(*thread);
+            stack.extend(
+                frames
+                    .iter()
+                    .filter(|n| !filters.iter().any(|f| f.matches(FilterKind::Hide, n))),
+            );
+            filtered.add_stack(&stack, value);
+        });
+        filtered.root.sort_recursive();
+        filtered
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FilterKind {
+    /// Keep only stacks with a matching frame.
+    Focus,
+    /// Drop stacks with a matching frame.
+    Ignore,
+    /// Remove matching frames, attaching their children to the parent.
+    Hide,
+}
+
+impl FilterKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterKind::Focus => "focus",
+            FilterKind::Ignore => "ignore",
+            FilterKind::Hide => "hide",
+        }
+    }
+}
+
+enum Matcher {
+    Name(Regex),
+    Type(FrameType),
+    OtherTypes(FrameType),
+}
+
+impl Matcher {
+    fn is_match(&self, node: &FlameNode) -> bool {
+        match self {
+            Matcher::Name(regex) => regex.is_match(&node.name),
+            Matcher::Type(t) => node.frame_type == *t,
+            Matcher::OtherTypes(t) => node.frame_type != *t,
+        }
+    }
+}
+
+/// A pprof-style stack filter. Thread names are never matched.
+pub struct StackFilter {
+    pub kind: FilterKind,
+    /// Regex or frame type description shown in the filter bar.
+    pub pattern: String,
+    matcher: Matcher,
+    pub enabled: bool,
+}
+
+impl StackFilter {
+    pub fn new(kind: FilterKind, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            kind,
+            pattern: pattern.to_string(),
+            matcher: Matcher::Name(Regex::new(pattern)?),
+            enabled: true,
+        })
+    }
+
+    /// Hides every frame of type `frame_type`, or with `only`, every frame
+    /// of any other type.
+    pub fn frame_type(frame_type: FrameType, only: bool) -> Self {
+        let (pattern, matcher) = if only {
+            (
+                format!("not [{}]", frame_type.label()),
+                Matcher::OtherTypes(frame_type),
+            )
+        } else {
+            (
+                format!("[{}]", frame_type.label()),
+                Matcher::Type(frame_type),
+            )
+        };
+        Self {
+            kind: FilterKind::Hide,
+            pattern,
+            matcher,
+            enabled: true,
+        }
+    }
+
+    fn matches(&self, kind: FilterKind, node: &FlameNode) -> bool {
+        self.enabled && self.kind == kind && self.matcher.is_match(node)
+    }
+}
+
+/// Callers and callees of one function, merged across its call sites.
+/// Both trees are rooted at the function and share its total.
+pub struct Butterfly {
+    pub name: String,
+    /// Inverted: children of the root are the direct callers, down to the
+    /// thread names.
+    pub callers: FlameNode,
+    pub callees: FlameNode,
+}
+
+/// Samples attributed to one function across the whole tree.
+pub struct FunctionStats {
+    pub name: String,
+    pub frame_type: FrameType,
+    pub self_value: i64,
+    /// Samples with the function anywhere on the stack, counting recursive
+    /// calls once.
+    pub total_value: i64,
+}
+
+/// Namespace of a frame label: the crate or outermost C++ namespace of
+/// `a::b::c`, the Go package of `path/pkg.Func`, the Java package of
+/// `com.app.Class.method`, or the module of an unsymbolized `lib.so+0x…`.
+pub fn package(label: &str) -> &str {
+    let name = label.split(" [").next().unwrap_or(label);
+    if let Some((module, _)) = name.split_once("+0x") {
+        return module;
+    }
+    let name = name.split('(').next().unwrap_or(name);
+    if let Some((first, _)) = name.split_once("::") {
+        return first;
+    }
+    if let Some(slash) = name.rfind('/') {
+        return name[slash..].find('.').map_or(name, |i| &name[..slash + i]);
+    }
+    let mut dots = name.rmatch_indices('.').map(|(i, _)| i);
+    match (dots.next(), dots.next()) {
+        (Some(_), Some(class)) => &name[..class],
+        (Some(last), None) => &name[..last],
+        _ => name,
+    }
 }
 
 pub struct FrameRect {
@@ -123,13 +899,20 @@ pub struct FrameRect {
     pub width: u16,
     pub depth: usize,
     pub name: String,
+    pub frame_type: FrameType,
+    pub module: String,
     pub self_value: i64,
     pub total_value: i64,
+    /// Total of the matching baseline frame, set only in diff mode.
+    pub baseline_value: Option<i64>,
     pub palette_index: usize,
 }
 
+/// Lays out the subtree of `node`. When `baseline` is given, every frame
+/// also carries the total of the frame at the same path in it.
 pub fn layout_frames(
     node: &FlameNode,
+    baseline: Option<&FlameNode>,
     area_width: u16,
     forced_palette: Option<usize>,
 ) -> Vec<FrameRect> {
@@ -138,52 +921,102 @@ pub fn layout_frames(
     }
     let scale = area_width as f64 / node.total_value as f64;
     let mut frames = Vec::new();
-    layout_recursive(node, 0.0, 0, scale, forced_palette, &mut frames);
+    let layout = Layout {
+        scale,
+        diff: baseline.is_some(),
+    };
+    layout.recurse(node, baseline, 0.0, 0, forced_palette, &mut frames);
     frames
 }
 
-fn layout_recursive(
-    node: &FlameNode,
-    x_float: f64,
-    depth: usize,
+struct Layout {
     scale: f64,
-    palette: Option<usize>,
-    frames: &mut Vec<FrameRect>,
-) {
-    let x_end = x_float + node.total_value as f64 * scale;
-    let x = x_float.round() as u16;
-    let width = (x_end.round() as u16).saturating_sub(x);
+    diff: bool,
+}
 
-    if width == 0 {
-        return;
-    }
+impl Layout {
+    fn recurse(
+        &self,
+        node: &FlameNode,
+        base: Option<&FlameNode>,
+        x_float: f64,
+        depth: usize,
+        palette: Option<usize>,
+        frames: &mut Vec<FrameRect>,
+    ) {
+        let scale = self.scale;
+        let x_end = x_float + node.total_value as f64 * scale;
+        let x = x_float.round() as u16;
+        let width = (x_end.round() as u16).saturating_sub(x);
 
-    let palette_index = palette.unwrap_or(0);
+        if width == 0 {
+            return;
+        }
 
-    frames.push(FrameRect {
-        x,
-        width,
-        depth,
-        name: node.name.clone(),
-        self_value: node.self_value,
-        total_value: node.total_value,
-        palette_index,
-    });
+        let palette_index = palette.unwrap_or(0);
 
-    let mut child_x = x_float;
-    for (i, child) in node.children.iter().enumerate() {
-        let child_palette = Some(if depth == 0 && palette.is_none() {
-            i
-        } else {
-            palette_index
+        frames.push(FrameRect {
+            x,
+            width,
+            depth,
+            name: node.name.clone(),
+            frame_type: node.frame_type,
+            module: node.module.clone(),
+            self_value: node.self_value,
+            total_value: node.total_value,
+            baseline_value: self.diff.then(|| base.map_or(0, |b| b.total_value)),
+            palette_index,
         });
-        layout_recursive(child, child_x, depth + 1, scale, child_palette, frames);
-        child_x += child.total_value as f64 * scale;
+
+        let mut child_x = x_float;
+        for (i, child) in node.children.iter().enumerate() {
+            let child_palette = Some(if depth == 0 && palette.is_none() {
+                i
+            } else {
+                palette_index
+            });
+            let child_base = base.and_then(|b| b.child_by_name(&child.name));
+            self.recurse(child, child_base, child_x, depth + 1, child_palette, frames);
+            child_x += child.total_value as f64 * scale;
+        }
     }
 }
 
+/// Index path (relative to `node`) of the frame that [`layout_frames`]
+/// places at `column` of row `depth`.
+pub fn frame_path_at(
+    node: &FlameNode,
+    area_width: u16,
+    depth: usize,
+    column: u16,
+) -> Option<Vec<usize>> {
+    if node.total_value <= 0 {
+        return None;
+    }
+    let scale = area_width as f64 / node.total_value as f64;
+    let mut node = node;
+    let mut x = 0.0;
+    let mut path = Vec::with_capacity(depth);
+    'rows: while path.len() < depth {
+        let mut child_x = x;
+        for (i, child) in node.children.iter().enumerate() {
+            let child_end = child_x + child.total_value as f64 * scale;
+            if (child_x.round() as u16..child_end.round() as u16).contains(&column) {
+                path.push(i);
+                node = child;
+                x = child_x;
+                continue 'rows;
+            }
+            child_x = child_end;
+        }
+        return None;
+    }
+    Some(path)
+}
+
 pub fn cursor_frame_rect(
     zoom_root: &FlameNode,
+    baseline: Option<&FlameNode>,
     cursor_path: &[usize],
     area_width: u16,
     forced_palette: Option<usize>,
@@ -193,6 +1026,7 @@ pub fn cursor_frame_rect(
     }
     let scale = area_width as f64 / zoom_root.total_value as f64;
     let mut node = zoom_root;
+    let mut base = baseline;
     let mut x_acc = 0.0;
     let mut palette_index = forced_palette.unwrap_or(0);
 
@@ -205,6 +1039,7 @@ pub fn cursor_frame_rect(
                 palette_index = idx;
             }
             node = &node.children[idx];
+            base = base.and_then(|b| b.child_by_name(&node.name));
         } else {
             return None;
         }
@@ -218,8 +1053,393 @@ pub fn cursor_frame_rect(
         width,
         depth: cursor_path.len(),
         name: node.name.clone(),
+        frame_type: node.frame_type,
+        module: node.module.clone(),
         self_value: node.self_value,
         total_value: node.total_value,
+        baseline_value: baseline.map(|_| base.map_or(0, |b| b.total_value)),
         palette_index,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn inverted_groups_by_leaf() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "a", "malloc"]), 3);
+        fg.add_stack(&stack(&["t1", "main", "b", "malloc"]), 2);
+        fg.add_stack(&stack(&["t1", "main", "b"]), 1);
+
+        let inv = fg.inverted();
+        let thread = inv.root.child_by_name("t1").unwrap();
+        assert_eq!(thread.total_value, 6);
+
+        let malloc = thread.child_by_name("malloc").unwrap();
+        assert_eq!(malloc.total_value, 5);
+        assert_eq!(malloc.child_by_name("a").unwrap().total_value, 3);
+        assert_eq!(malloc.child_by_name("b").unwrap().total_value, 2);
+
+        let b = thread.child_by_name("b").unwrap();
+        assert_eq!(b.total_value, 1);
+        assert_eq!(b.child_by_name("main").unwrap().self_value, 1);
+    }
+
+    #[test]
+    fn layout_carries_baseline_values() {
+        let mut before = FlameGraph::new();
+        before.add_stack(&stack(&["t1", "main", "a"]), 4);
+        let mut after = FlameGraph::new();
+        after.add_stack(&stack(&["t1", "main", "a"]), 2);
+        after.add_stack(&stack(&["t1", "main", "b"]), 2);
+
+        let frames = layout_frames(&after.root, Some(&before.root), 100, None);
+        let value_of = |name: &str| {
+            frames
+                .iter()
+                .find(|f| f.name == name)
+                .and_then(|f| f.baseline_value)
+        };
+        assert_eq!(value_of("a"), Some(4));
+        assert_eq!(value_of("b"), Some(0));
+
+        let plain = layout_frames(&after.root, None, 100, None);
+        assert!(plain.iter().all(|f| f.baseline_value.is_none()));
+    }
+
+    #[test]
+    fn package_of_labels() {
+        assert_eq!(
+            package("std::vector<int>::push_back(int const&) [Native]"),
+            "std"
+        );
+        assert_eq!(
+            package("tokio::runtime::park [Native] / tokio::x [Native] [Inline]"),
+            "tokio"
+        );
+        assert_eq!(package("net/http.(*conn).serve [Go]"), "net/http");
+        assert_eq!(package("com.app.Main.run [JVM]"), "com.app");
+        assert_eq!(package("json.loads [Python]"), "json");
+        assert_eq!(
+            package("libc.so.6+0x00000000000a1b2c [Native]"),
+            "libc.so.6"
+        );
+        assert_eq!(package("main [Native]"), "main");
+    }
+
+    #[test]
+    fn frame_path_at_finds_laid_out_frames() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "a"]), 3);
+        fg.add_stack(&stack(&["t1", "main", "b"]), 1);
+        fg.add_stack(&stack(&["t2", "run"]), 4);
+
+        for fr in layout_frames(&fg.root, None, 80, None) {
+            for column in [fr.x, fr.x + fr.width - 1] {
+                let path = frame_path_at(&fg.root, 80, fr.depth, column).unwrap();
+                assert_eq!(path.len(), fr.depth);
+                assert_eq!(fg.root.follow_indices(&path).name, fr.name);
+            }
+        }
+        assert!(frame_path_at(&fg.root, 80, 3, 50).is_none());
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "a"]), 4);
+        fg.add_stack(&stack(&["t1", "main"]), 1);
+        fg.add_stack(
+            &[
+                Frame {
+                    name: "t2".into(),
+                    frame_type: FrameType::Unknown,
+                    module: String::new(),
+                },
+                Frame {
+                    name: "run".into(),
+                    frame_type: FrameType::Kernel,
+                    module: "vmlinux".into(),
+                },
+            ],
+            2,
+        );
+
+        let mut buf = Vec::new();
+        fg.root.encode_into(&mut buf);
+        let decoded = FlameNode::decode(&mut buf.as_slice(), 3).unwrap();
+
+        assert_eq!(decoded.total_value, 7);
+        let main = decoded.find_path(&stack(&["t1", "main"])).unwrap();
+        assert_eq!((main.self_value, main.total_value), (1, 5));
+        assert_eq!(main.child_by_name("a").unwrap().total_value, 4);
+        let run = decoded.find_path(&stack(&["t2", "run"])).unwrap();
+        assert_eq!(run.frame_type, FrameType::Kernel);
+        assert_eq!(run.module, "vmlinux");
+        assert!(FlameNode::decode(&mut &buf[..buf.len() - 1], 3).is_none());
+    }
+
+    #[test]
+    fn subtract_drops_empty_nodes() {
+        let mut old = FlameGraph::new();
+        old.add_stack(&stack(&["t1", "main", "a"]), 2);
+        let mut fg = old.clone();
+        fg.add_stack(&stack(&["t1", "main", "b"]), 3);
+
+        fg.root.subtract(&old.root);
+        assert_eq!(fg.root.total_value, 3);
+        let main = fg.root.find_path(&stack(&["t1", "main"])).unwrap();
+        assert!(main.child_by_name("a").is_none());
+        assert_eq!(main.child_by_name("b").unwrap().total_value, 3);
+        assert_eq!(main.child_position("b"), Some(0));
+    }
+
+    #[test]
+    fn prune_folds_small_subtrees_into_other() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "hot", "leaf"]), 100);
+        for i in 0..20 {
+            fg.add_stack(&stack(&["t1", "main", &format!("cold{i}"), "x"]), 1);
+        }
+        let before = fg.root.node_count();
+        assert_eq!(before, 45);
+
+        let (removed, folded) = fg.prune_to(20);
+        assert_eq!(fg.root.node_count(), before - removed);
+        assert!(fg.root.node_count() <= 18);
+        assert_eq!(folded, 20);
+        assert_eq!(fg.root.total_value, 120);
+
+        let main = fg.root.find_path(&stack(&["t1", "main"])).unwrap();
+        assert_eq!(main.total_value, 120);
+        assert_eq!(main.child_by_name("hot").unwrap().total_value, 100);
+        let other = main.child_by_name(OTHER_FRAME).unwrap();
+        assert_eq!((other.total_value, other.self_value), (20, 20));
+
+        let mut expired = FlameGraph::new();
+        expired.add_stack(&stack(&["t1", "main", "cold3", "x"]), 1);
+        fg.root.subtract(&expired.root);
+        let main = fg.root.find_path(&stack(&["t1", "main"])).unwrap();
+        assert_eq!(main.child_by_name(OTHER_FRAME).unwrap().total_value, 19);
+    }
+
+    #[test]
+    fn fold_to_follows_pruning() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "hot"]), 20);
+        fg.add_stack(&stack(&["t1", "main", "cold", "x"]), 1);
+        let mut interval = fg.clone();
+        fg.prune_to(5);
+        assert!(fg.root.find_path(&stack(&["t1", "main", "cold"])).is_none());
+        interval.root.fold_to(&fg.root);
+
+        // `cold` comes back after it was folded.
+        let mut later = FlameGraph::new();
+        later.add_stack(&stack(&["t1", "main", "cold"]), 2);
+        assert_eq!(fg.root.merge(later.root), 1);
+
+        // `hot` and `[other]` empty out.
+        assert_eq!(fg.root.subtract(&interval.root), 2);
+        let main = fg.root.find_path(&stack(&["t1", "main"])).unwrap();
+        assert_eq!(main.total_value, 2);
+        assert_eq!(main.child_by_name("cold").unwrap().total_value, 2);
+        assert!(main.child_by_name(OTHER_FRAME).is_none());
+    }
+
+    #[test]
+    fn functions_count_recursion_once() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "walk", "walk", "visit"]), 3);
+        fg.add_stack(&stack(&["t1", "main", "walk"]), 1);
+        fg.add_stack(&stack(&["t2", "walk"]), 2);
+
+        let functions = fg.functions();
+        let get = |name: &str| functions.iter().find(|f| f.name == name).unwrap();
+        assert_eq!((get("walk").self_value, get("walk").total_value), (3, 6));
+        assert_eq!((get("visit").self_value, get("visit").total_value), (3, 3));
+        assert_eq!(get("main").total_value, 4);
+        assert!(functions.iter().all(|f| f.name != "t1"));
+    }
+
+    #[test]
+    fn filters_focus_ignore_and_hide() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "alloc", "malloc"]), 3);
+        fg.add_stack(&stack(&["t1", "main", "parse"]), 2);
+        fg.add_stack(&stack(&["t1", "gc", "alloc"]), 4);
+
+        let focus = [StackFilter::new(FilterKind::Focus, "^alloc$").unwrap()];
+        let filtered = fg.filtered(&focus);
+        assert_eq!(filtered.root.total_value, 7);
+        assert!(
+            filtered
+                .root
+                .find_path(&stack(&["t1", "main", "parse"]))
+                .is_none()
+        );
+
+        let mut ignore_gc = vec![
+            StackFilter::new(FilterKind::Focus, "^alloc$").unwrap(),
+            StackFilter::new(FilterKind::Ignore, "gc").unwrap(),
+        ];
+        assert_eq!(fg.filtered(&ignore_gc).root.total_value, 3);
+        ignore_gc[1].enabled = false;
+        assert_eq!(fg.filtered(&ignore_gc).root.total_value, 7);
+
+        let hide = [StackFilter::new(FilterKind::Hide, "^main$").unwrap()];
+        let filtered = fg.filtered(&hide);
+        assert_eq!(filtered.root.total_value, 9);
+        let node = filtered
+            .root
+            .find_path(&stack(&["t1", "alloc", "malloc"]))
+            .unwrap();
+        assert_eq!(node.total_value, 3);
+        assert_eq!(
+            filtered
+                .root
+                .find_path(&stack(&["t1", "parse"]))
+                .unwrap()
+                .total_value,
+            2
+        );
+    }
+
+    #[test]
+    fn frame_type_filters_reattach_children() {
+        let frame = |name: &str, frame_type| Frame {
+            name: name.to_string(),
+            frame_type,
+            module: String::new(),
+        };
+        let mut fg = FlameGraph::new();
+        fg.add_stack(
+            &[
+                frame("t1", FrameType::Unknown),
+                frame("run", FrameType::Jvm),
+                frame("read", FrameType::Native),
+                frame("sys_read", FrameType::Kernel),
+            ],
+            3,
+        );
+        fg.add_stack(
+            &[
+                frame("t1", FrameType::Unknown),
+                frame("run", FrameType::Jvm),
+                frame("work", FrameType::Jvm),
+            ],
+            2,
+        );
+        assert_eq!(
+            fg.frame_types(),
+            vec![FrameType::Native, FrameType::Kernel, FrameType::Jvm]
+        );
+
+        let no_kernel = fg.filtered(&[StackFilter::frame_type(FrameType::Kernel, false)]);
+        let read = no_kernel
+            .root
+            .find_path(&stack(&["t1", "run", "read"]))
+            .unwrap();
+        assert_eq!((read.self_value, read.total_value), (3, 3));
+        assert_eq!(read.frame_type, FrameType::Native);
+
+        let jvm_only = fg.filtered(&[StackFilter::frame_type(FrameType::Jvm, true)]);
+        let run = jvm_only.root.find_path(&stack(&["t1", "run"])).unwrap();
+        assert_eq!((run.self_value, run.total_value), (3, 5));
+        assert_eq!(run.child_by_name("work").unwrap().total_value, 2);
+    }
+
+    #[test]
+    fn butterfly_merges_call_sites() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "parse", "malloc", "brk"]), 3);
+        fg.add_stack(&stack(&["t1", "main", "render", "malloc"]), 2);
+        fg.add_stack(&stack(&["t2", "malloc", "malloc", "mmap"]), 4);
+        fg.add_stack(&stack(&["t2", "idle"]), 7);
+
+        let b = fg.butterfly("malloc");
+        assert_eq!(b.callers.total_value, 9);
+        assert_eq!(b.callees.total_value, 9);
+        assert_eq!(b.callees.self_value, 2);
+        assert_eq!(
+            b.callers
+                .find_path(&stack(&["parse", "main", "t1"]))
+                .unwrap()
+                .total_value,
+            3
+        );
+        assert_eq!(b.callers.child_by_name("t2").unwrap().total_value, 4);
+        assert_eq!(
+            b.callees
+                .find_path(&stack(&["malloc", "mmap"]))
+                .unwrap()
+                .total_value,
+            4
+        );
+    }
+
+    #[test]
+    fn collapse_recursion_direct_and_cycles() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "parse", "parse", "parse", "lex"]), 2);
+        fg.add_stack(&stack(&["t1", "walk", "visit", "walk", "visit", "leaf"]), 3);
+
+        let direct = fg.collapse_recursion(false);
+        let parse = direct.root.find_path(&stack(&["t1", "parse"])).unwrap();
+        assert_eq!(parse.folded_depth, 2);
+        assert_eq!(parse.child_by_name("lex").unwrap().total_value, 2);
+        assert!(
+            direct
+                .root
+                .find_path(&stack(&["t1", "walk", "visit", "walk"]))
+                .is_some()
+        );
+
+        let cycles = fg.collapse_recursion(true);
+        let walk = cycles.root.find_path(&stack(&["t1", "walk"])).unwrap();
+        assert_eq!(walk.folded_depth, 1);
+        assert_eq!(walk.total_value, 3);
+        assert_eq!(
+            cycles
+                .root
+                .find_path(&stack(&["t1", "walk", "visit", "leaf"]))
+                .unwrap()
+                .folded_depth,
+            0
+        );
+        assert_eq!(cycles.root.total_value, 5);
+    }
+
+    #[test]
+    fn inverted_keeps_folded_depth() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "parse", "parse", "parse", "lex"]), 2);
+
+        let inv = fg.collapse_recursion(false).inverted();
+        let parse = inv.root.find_path(&stack(&["t1", "lex", "parse"])).unwrap();
+        assert_eq!(parse.folded_depth, 2);
+        let main = parse.child_by_name("main").unwrap();
+        assert_eq!((main.folded_depth, main.total_value), (0, 2));
+    }
+
+    #[test]
+    fn find_matches_counts_nested_once() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "lock", "lock_slow"]), 3);
+        fg.add_stack(&stack(&["t1", "main", "lock"]), 2);
+        fg.add_stack(&stack(&["t1", "main", "work"]), 5);
+        fg.root.sort_recursive();
+
+        let (matches, value) = fg.root.find_matches(|name| name.contains("lock"));
+        assert_eq!(value, 5);
+        assert_eq!(matches.len(), 3);
+        for path in &matches {
+            assert!(fg.root.follow_indices(path).name.contains("lock"));
+        }
+    }
+}
@@ -2,24 +2,37 @@ use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock, mpsc};
 use tonic::{Request, Response, Status};
 
-use crate::flamegraph::FlameGraph;
+use crate::flamegraph::{FlameGraph, Frame, FrameType, Stack, Timestamps};
+use crate::normalize::Normalizer;
 use crate::storage::SymbolStore;
 use crate::tui::event::Event;
 use eprofiler_proto::opentelemetry::proto::collector::profiles::v1development as collector;
 use eprofiler_proto::opentelemetry::proto::common::v1 as common;
 use eprofiler_proto::opentelemetry::proto::profiles::v1development as profiles;
 
+/// Spacing of the timestamps spread over a profile for a sample without
+/// any, each weighted with a share of the sample's value.
+const SPREAD_STEP_NS: u64 = 100_000_000;
+/// Most timestamps spread over a profile for one sample.
+const MAX_SPREAD_TIMESTAMPS: u64 = 100;
+
 pub struct ProfilesServer {
     event_tx: mpsc::Sender<Event>,
     store: Arc<SymbolStore>,
+    normalizer: Arc<Normalizer>,
     known_basenames: Arc<RwLock<HashSet<String>>>,
 }
 
 impl ProfilesServer {
-    pub fn new(event_tx: mpsc::Sender<Event>, store: Arc<SymbolStore>) -> Self {
+    pub fn new(
+        event_tx: mpsc::Sender<Event>,
+        store: Arc<SymbolStore>,
+        normalizer: Arc<Normalizer>,
+    ) -> Self {
         Self {
             event_tx,
             store,
+            normalizer,
             known_basenames: Arc::new(RwLock::new(HashSet::new())),
         }
     }
@@ -53,32 +66,29 @@ impl<'a> Dict<'a> {
     }
 
     fn mapping_basename(&self, location: &profiles::Location) -> &'a str {
+        self.module(location).unwrap_or("[unknown]")
+    }
+
+    fn module(&self, location: &profiles::Location) -> Option<&'a str> {
         self.d
             .mapping_table
             .get(location.mapping_index as usize)
             .filter(|_| location.mapping_index > 0)
             .and_then(|m| self.str(m.filename_strindex))
             .map(|full| full.rsplit('/').next().unwrap_or(full))
-            .unwrap_or("[unknown]")
+            .filter(|basename| !basename.is_empty())
     }
 
-    fn frame_type(&self, location: &profiles::Location) -> &str {
-        self.find_attr_value(&location.attribute_indices, "profile.frame.type")
-            .map(|s| match s {
-                "native" => "Native",
-                "kernel" => "Kernel",
-                "jvm" => "JVM",
-                "cpython" => "Python",
-                "php" | "phpjit" => "PHP",
-                "ruby" => "Ruby",
-                "perl" => "Perl",
-                "v8js" => "JS",
-                "dotnet" => ".NET",
-                "beam" => "Beam",
-                "go" => "Go",
-                other => other,
-            })
-            .unwrap_or("Unknown")
+    /// Frame type and the tag shown in the frame label. Unrecognized types
+    /// keep their attribute value as the tag.
+    fn frame_type(&self, location: &profiles::Location) -> (FrameType, &'a str) {
+        match self.find_attr_value(&location.attribute_indices, "profile.frame.type") {
+            Some(value) => match FrameType::from_attribute(value) {
+                Some(t) => (t, t.label()),
+                None => (FrameType::Unknown, value),
+            },
+            None => (FrameType::Unknown, FrameType::Unknown.label()),
+        }
     }
 
     fn thread_name(&self, sample: &profiles::Sample) -> &'a str {
@@ -125,25 +135,36 @@ impl<'a> Dict<'a> {
     }
 }
 
-/// Pre-resolves the location table into human-readable strings.
-fn pre_resolve_locations(dict: &Dict, store: &SymbolStore) -> Vec<String> {
+/// Pre-resolves the location table into human-readable frames, with
+/// function names rewritten by `normalizer`.
+fn pre_resolve_locations(dict: &Dict, store: &SymbolStore, normalizer: &Normalizer) -> Vec<Frame> {
     dict.d
         .location_table
         .iter()
         .map(|location| {
-            let tag = dict.frame_type(location);
-            if location.lines.is_empty() {
-                if tag == "Native"
+            let (frame_type, tag) = dict.frame_type(location);
+            let module = dict.module(location).unwrap_or_default().to_string();
+            let name = if location.lines.is_empty() {
+                if frame_type == FrameType::Native
                     && let Some(names) = symbolize_native(store, location, dict)
                 {
-                    return names
+                    let name = names
                         .iter()
                         .enumerate()
                         .map(|(i, n)| {
-                            format!("{n} [Native]{}", if i > 0 { " [Inline]" } else { "" })
+                            format!(
+                                "{} [Native]{}",
+                                normalizer.apply(n),
+                                if i > 0 { " [Inline]" } else { "" }
+                            )
                         })
                         .collect::<Vec<_>>()
                         .join(" / ");
+                    return Frame {
+                        name,
+                        frame_type,
+                        module,
+                    };
                 }
                 let basename = dict.mapping_basename(location);
                 format!("{basename}+0x{:016x} [{tag}]", location.address)
@@ -155,12 +176,17 @@ fn pre_resolve_locations(dict: &Dict, store: &SymbolStore) -> Vec<String> {
                     .map(|(i, line)| {
                         format!(
                             "{} [{tag}]{}",
-                            dict.func_name(line),
+                            normalizer.apply(dict.func_name(line)),
                             if i > 0 { " [Inline]" } else { "" }
                         )
                     })
                     .collect::<Vec<_>>()
                     .join(" / ")
+            };
+            Frame {
+                name,
+                frame_type,
+                module,
             }
         })
         .collect()
@@ -169,6 +195,7 @@ fn pre_resolve_locations(dict: &Dict, store: &SymbolStore) -> Vec<String> {
 fn process_export(
     req: collector::ExportProfilesServiceRequest,
     store: &SymbolStore,
+    normalizer: &Normalizer,
     known: &RwLock<HashSet<String>>,
     event_tx: &mpsc::Sender<Event>,
 ) {
@@ -178,37 +205,42 @@ fn process_export(
     let dict = Dict::new(raw_dict);
 
     let mut flamegraph = FlameGraph::new();
-    let mut stack_cache: HashMap<i32, Vec<String>> = HashMap::new();
-    let location_cache = pre_resolve_locations(&dict, store);
+    let mut stack_cache: HashMap<i32, Stack> = HashMap::new();
+    let location_cache = pre_resolve_locations(&dict, store, normalizer);
     let mut sample_count: u64 = 0;
-    let mut thread_timestamps: HashMap<String, Vec<u64>> = HashMap::new();
+    let mut timestamps = Timestamps::new();
 
-    let samples = req
-        .resource_profiles
-        .iter()
-        .flat_map(|rp| &rp.scope_profiles)
-        .flat_map(|sp| &sp.profiles)
-        .flat_map(|p| &p.samples);
+    let samples = req.resource_profiles.iter().flat_map(|rp| {
+        let resource = resource_name(rp);
+        rp.scope_profiles
+            .iter()
+            .flat_map(|sp| &sp.profiles)
+            .flat_map(move |p| p.samples.iter().map(move |sample| (resource, p, sample)))
+    });
 
-    for sample in samples {
+    for (resource, profile, sample) in samples {
         let stack = stack_cache.entry(sample.stack_index).or_insert_with(|| {
             let idx = sample.stack_index as usize;
             if idx == 0 || idx >= dict.d.stack_table.len() {
-                return Vec::new();
+                return Stack::from([]);
             }
 
-            let mut frames: Vec<String> = dict.d.stack_table[idx]
+            let mut frames: Vec<Frame> = dict.d.stack_table[idx]
                 .location_indices
                 .iter()
                 .filter_map(|&loc_idx| location_cache.get(loc_idx as usize).cloned())
                 .collect();
             frames.reverse();
 
-            let comm = dict.thread_name(sample).to_string();
+            let comm = Frame {
+                name: dict.thread_name(sample).to_string(),
+                frame_type: FrameType::Unknown,
+                module: String::new(),
+            };
             let mut result = Vec::with_capacity(frames.len() + 1);
             result.push(comm);
             result.extend(frames);
-            result
+            Stack::from(result)
         });
 
         if stack.is_empty() {
@@ -216,10 +248,6 @@ fn process_export(
         }
 
         let value = if !sample.timestamps_unix_nano.is_empty() {
-            thread_timestamps
-                .entry(stack[0].clone())
-                .or_default()
-                .extend_from_slice(&sample.timestamps_unix_nano);
             sample.timestamps_unix_nano.len() as i64
         } else if !sample.values.is_empty() {
             sample.values.iter().sum::<i64>().max(1)
@@ -227,6 +255,26 @@ fn process_export(
             1
         };
 
+        let (times, approximated) = if !sample.timestamps_unix_nano.is_empty() {
+            let times = sample.timestamps_unix_nano.iter().map(|&ts| (ts, 1));
+            (times.collect(), false)
+        } else {
+            (spread_timestamps(profile, value as u64), true)
+        };
+        if !times.is_empty() {
+            let recorded = timestamps.entry(resource.to_string()).or_default();
+            recorded.approximated |= approximated;
+            recorded
+                .threads
+                .entry(stack[0].name.clone())
+                .or_default()
+                .extend(
+                    times
+                        .into_iter()
+                        .map(|(ts, weight)| (ts, weight, Arc::clone(stack))),
+                );
+        }
+
         flamegraph.add_stack(stack, value);
         sample_count += value as u64;
     }
@@ -238,10 +286,46 @@ fn process_export(
     let _ = event_tx.send(Event::ProfileUpdate {
         flamegraph,
         samples: sample_count,
-        timestamps: thread_timestamps,
+        timestamps,
     });
 }
 
+/// Evenly spaced timestamps over the profile's interval standing in for
+/// `count` occurrences of a sample that carried none, with weights adding
+/// up to `count`; empty when the profile has no interval.
+fn spread_timestamps(profile: &profiles::Profile, count: u64) -> Vec<(u64, u64)> {
+    if profile.time_unix_nano == 0 || profile.duration_nano == 0 {
+        return Vec::new();
+    }
+    let count = count.max(1);
+    let n = (profile.duration_nano / SPREAD_STEP_NS)
+        .clamp(1, MAX_SPREAD_TIMESTAMPS)
+        .min(count);
+    let step = profile.duration_nano / n;
+    (0..n)
+        .map(|i| {
+            let weight = count / n + u64::from(i < count % n);
+            (profile.time_unix_nano + i * step + step / 2, weight)
+        })
+        .collect()
+}
+
+/// Identifies the agent a profile came from, by the first of its
+/// `service.name`, `host.name` and `host.id` resource attributes.
+fn resource_name(rp: &profiles::ResourceProfiles) -> &str {
+    let attributes = rp.resource.as_ref().map_or(&[][..], |r| &r.attributes);
+    ["service.name", "host.name", "host.id"]
+        .iter()
+        .find_map(|key| {
+            let attr = attributes.iter().find(|kv| kv.key == *key)?;
+            match attr.value.as_ref()?.value.as_ref()? {
+                common::any_value::Value::StringValue(s) if !s.is_empty() => Some(s.as_str()),
+                _ => None,
+            }
+        })
+        .unwrap_or("[unknown]")
+}
+
 #[tonic::async_trait]
 impl collector::profiles_service_server::ProfilesService for ProfilesServer {
     async fn export(
@@ -250,12 +334,14 @@ impl collector::profiles_service_server::ProfilesService for ProfilesServer {
     ) -> Result<Response<collector::ExportProfilesServiceResponse>, Status> {
         tokio::task::spawn_blocking({
             let store = self.store.clone();
+            let normalizer = Arc::clone(&self.normalizer);
             let known_basenames = Arc::clone(&self.known_basenames);
             let event_tx = self.event_tx.clone();
             move || {
                 process_export(
                     request.into_inner(),
                     store.as_ref(),
+                    &normalizer,
                     &known_basenames,
                     &event_tx,
                 );
@@ -289,9 +375,10 @@ pub async fn start_server(
     event_tx: mpsc::Sender<Event>,
     addr: &str,
     store: Arc<SymbolStore>,
+    normalizer: Arc<Normalizer>,
 ) -> Result<(), tonic::transport::Error> {
     let addr = addr.parse().expect("invalid gRPC listen address");
-    let server = ProfilesServer::new(event_tx, store);
+    let server = ProfilesServer::new(event_tx, store, normalizer);
 
     tonic::transport::Server::builder()
         .add_service(
@@ -310,8 +397,9 @@ mod tests {
 
     use collector::ExportProfilesServiceRequest;
     use collector::profiles_service_client::ProfilesServiceClient;
-    use common::AnyValue;
     use common::any_value;
+    use common::{AnyValue, KeyValue};
+    use eprofiler_proto::opentelemetry::proto::resource::v1::Resource;
     use profiles::{
         Function, KeyValueAndUnit, Line, Location, Profile, ProfilesDictionary, ResourceProfiles,
         Sample, ScopeProfiles, Stack,
@@ -324,7 +412,7 @@ mod tests {
         let store = Arc::new(crate::storage::SymbolStore::open(tmp.path()).unwrap());
         tokio::spawn(async move {
             let _tmp = tmp;
-            let server = ProfilesServer::new(tx, store);
+            let server = ProfilesServer::new(tx, store, Arc::new(Normalizer::default()));
             tonic::transport::Server::builder()
                 .add_service(collector::profiles_service_server::ProfilesServiceServer::new(server))
                 .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
@@ -413,6 +501,8 @@ mod tests {
                 scope_profiles: vec![ScopeProfiles {
                     profiles: vec![Profile {
                         samples: vec![sample],
+                        time_unix_nano: 5_000,
+                        duration_nano: 500_000_000,
                         ..Default::default()
                     }],
                     ..Default::default()
@@ -431,7 +521,18 @@ mod tests {
                 timestamps,
             } => {
                 assert_eq!(samples, 10);
-                assert!(timestamps.is_empty());
+                let recorded = &timestamps["[unknown]"];
+                assert!(recorded.approximated);
+                // One timestamp per 100ms, sharing the sample's value.
+                assert_eq!(
+                    recorded.threads["worker-1"]
+                        .iter()
+                        .map(|(ts, weight, _)| (*ts, *weight))
+                        .collect::<Vec<_>>(),
+                    (0..5)
+                        .map(|i| (5_000 + 50_000_000 + i * 100_000_000, 2))
+                        .collect::<Vec<_>>()
+                );
                 let thread = &flamegraph.root.children[0];
                 assert_eq!(thread.name, "worker-1");
                 assert_eq!(thread.total_value, 10);
@@ -461,6 +562,16 @@ mod tests {
         let req = ExportProfilesServiceRequest {
             dictionary: Some(build_dictionary()),
             resource_profiles: vec![ResourceProfiles {
+                resource: Some(Resource {
+                    attributes: vec![KeyValue {
+                        key: "service.name".into(),
+                        value: Some(AnyValue {
+                            value: Some(any_value::Value::StringValue("api".into())),
+                        }),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }),
                 scope_profiles: vec![ScopeProfiles {
                     profiles: vec![Profile {
                         samples: vec![sample],
@@ -482,10 +593,16 @@ mod tests {
                 timestamps,
             } => {
                 assert_eq!(samples, 5);
+                assert!(!timestamps["api"].approximated);
+                let recorded = &timestamps["api"].threads["worker-1"];
                 assert_eq!(
-                    timestamps.get("worker-1").unwrap(),
-                    &vec![100, 200, 300, 400, 500]
+                    recorded
+                        .iter()
+                        .map(|(ts, weight, _)| (*ts, *weight))
+                        .collect::<Vec<_>>(),
+                    vec![(100, 1), (200, 1), (300, 1), (400, 1), (500, 1)]
                 );
+                assert_eq!(recorded[0].2.last().unwrap().name, "do_work [Unknown]");
                 let thread = &flamegraph.root.children[0];
                 assert_eq!(thread.total_value, 5);
             }
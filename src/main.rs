@@ -1,3 +1,4 @@
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -7,18 +8,24 @@ use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 
 mod debug;
+mod demangle;
 mod error;
 mod flamegraph;
 mod grpc;
+mod markers;
+mod normalize;
 mod storage;
 mod symbolizer;
+mod trigger;
 mod tui;
 
 use error::Result;
+use normalize::{Normalizer, Preset};
 use storage::SymbolStore;
+use trigger::{Trigger, Watcher};
 use tui::Tui;
 use tui::event::{Event, EventHandler};
-use tui::state::{Action, State};
+use tui::state::{Action, DEFAULT_RETENTION_SECS, State};
 
 #[derive(Parser)]
 #[command(
@@ -32,6 +39,39 @@ struct Cli {
     /// typically ~/.local/share/eprofiler-tui on Linux)
     #[arg(short = 'd', long = "data-dir", value_name = "PATH")]
     data_dir: Option<PathBuf>,
+    /// Only keep the last SECS seconds of samples in the flamegraph
+    /// (default: accumulate until reset)
+    #[arg(short, long, value_name = "SECS")]
+    window: Option<u64>,
+    /// Maximum number of flamegraph nodes before the smallest subtrees are
+    /// folded into `[other]` (0 disables the limit)
+    #[arg(long, value_name = "N", default_value_t = 500_000)]
+    max_nodes: usize,
+    /// Seconds of samples kept in the flamescope, counted back from the
+    /// newest one (0 keeps everything)
+    #[arg(long, value_name = "SECS", default_value_t = DEFAULT_RETENTION_SECS)]
+    flamescope_retention: u64,
+    /// Frame name normalization rules (default: rules.conf in
+    /// $XDG_CONFIG_HOME/eprofiler-tui, if present)
+    #[arg(long, value_name = "PATH")]
+    rules: Option<PathBuf>,
+    /// Built-in normalization rules to apply before the rules file
+    /// (repeatable)
+    #[arg(long = "preset", value_enum, value_name = "NAME")]
+    presets: Vec<Preset>,
+    /// Accept markers posted to http://ADDR/markers, e.g.
+    /// `127.0.0.1:4320` (default: disabled)
+    #[arg(long, value_name = "ADDR")]
+    marker_addr: Option<SocketAddr>,
+    /// Snapshot the flamegraph when a condition holds, e.g.
+    /// `rate:api>500 for 10s` or `function:parse_json>20% for 5s`
+    /// (repeatable)
+    #[arg(long = "trigger", value_name = "SPEC")]
+    triggers: Vec<Trigger>,
+    /// Start without mouse capture, leaving text selection to the terminal
+    /// (toggle with `m`)
+    #[arg(long)]
+    no_mouse: bool,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -56,20 +96,31 @@ fn main() -> Result<()> {
     let listen_addr = format!("0.0.0.0:{}", cli.port);
     let storage_path = resolve_storage_path(cli.data_dir)?;
     let store = Arc::new(SymbolStore::open(&storage_path)?);
+    let normalizer = load_normalizer(cli.rules, &cli.presets)?;
     let events = EventHandler::new(100);
 
     spawn_grpc_server(
         Arc::clone(&store),
+        Arc::new(normalizer),
         listen_addr.clone(),
         events.sender.clone(),
     );
+    if let Some(addr) = cli.marker_addr {
+        markers::spawn_server(addr, events.sender.clone())?;
+    }
 
     let backend = CrosstermBackend::new(std::io::stderr());
     let terminal = Terminal::new(backend)?;
     let mut tui = Tui::new(terminal, events);
     tui.init()?;
 
-    let mut state = State::new(listen_addr, store.list_files()?);
+    let mut state = State::new(listen_addr, store.list_files()?, store.list_snapshots()?);
+    state.fg.set_window(cli.window.filter(|&secs| secs > 0));
+    state.fg.max_nodes = Some(cli.max_nodes).filter(|&n| n > 0);
+    state.fs.retention_secs = Some(cli.flamescope_retention).filter(|&secs| secs > 0);
+    state.triggers = Watcher::new(cli.triggers);
+    state.mouse = !cli.no_mouse;
+    tui.set_mouse_capture(state.mouse)?;
 
     while state.running {
         tui.draw(&mut state)?;
@@ -88,6 +139,21 @@ fn main() -> Result<()> {
                 state.exe.status = Some(format!("Removing {name}"));
                 spawn_symbol_remove(Arc::clone(&store), tui.events.sender.clone(), name, file_id);
             }
+            Action::SaveSnapshot(snapshot) => {
+                spawn_snapshot_save(Arc::clone(&store), tui.events.sender.clone(), snapshot);
+            }
+            Action::SaveSnapshots { snapshots, removed } => {
+                for snapshot in snapshots {
+                    spawn_snapshot_save(Arc::clone(&store), tui.events.sender.clone(), snapshot);
+                }
+                for name in removed {
+                    spawn_snapshot_remove(Arc::clone(&store), tui.events.sender.clone(), name);
+                }
+            }
+            Action::RemoveSnapshot(name) => {
+                spawn_snapshot_remove(Arc::clone(&store), tui.events.sender.clone(), name);
+            }
+            Action::SetMouseCapture(enabled) => tui.set_mouse_capture(enabled)?,
         }
     }
 
@@ -110,15 +176,29 @@ fn resolve_storage_path(data_dir: Option<PathBuf>) -> Result<PathBuf> {
     Ok(path)
 }
 
+fn load_normalizer(rules: Option<PathBuf>, presets: &[Preset]) -> Result<Normalizer> {
+    let mut normalizer = Normalizer::from_presets(presets);
+    let path = rules.or_else(|| {
+        ProjectDirs::from("", "", "eprofiler-tui")
+            .map(|dirs| dirs.config_dir().join("rules.conf"))
+            .filter(|path| path.exists())
+    });
+    if let Some(path) = path {
+        normalizer.load(&path)?;
+    }
+    Ok(normalizer)
+}
+
 fn spawn_grpc_server(
     store: Arc<SymbolStore>,
+    normalizer: Arc<Normalizer>,
     listen_addr: String,
     event_tx: std::sync::mpsc::Sender<Event>,
 ) {
     std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
         rt.block_on(async {
-            if let Err(e) = grpc::start_server(event_tx, &listen_addr, store).await {
+            if let Err(e) = grpc::start_server(event_tx, &listen_addr, store, normalizer).await {
                 eprintln!("gRPC server error: {e}");
             }
         });
@@ -165,3 +245,29 @@ fn spawn_symbol_remove(
         });
     });
 }
+
+fn spawn_snapshot_save(
+    store: Arc<SymbolStore>,
+    sender: std::sync::mpsc::Sender<Event>,
+    snapshot: storage::Snapshot,
+) {
+    std::thread::spawn(move || {
+        let _ = sender.send(Event::SnapshotSaved {
+            error: store.save_snapshot(&snapshot).err(),
+            name: snapshot.name,
+        });
+    });
+}
+
+fn spawn_snapshot_remove(
+    store: Arc<SymbolStore>,
+    sender: std::sync::mpsc::Sender<Event>,
+    name: String,
+) {
+    std::thread::spawn(move || {
+        let _ = sender.send(Event::SnapshotRemoved {
+            error: store.remove_snapshot(&name).err(),
+            name,
+        });
+    });
+}
@@ -0,0 +1,208 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::tui::event::Event;
+
+/// Longest request body accepted as a marker label.
+const MAX_LABEL_BYTES: usize = 1024;
+/// Longest request line or header line accepted.
+const MAX_LINE_BYTES: u64 = 8 * 1024;
+/// Most header lines accepted in a request.
+const MAX_HEADERS: usize = 64;
+/// Most connections handled at once; more are turned away.
+const MAX_CONNECTIONS: usize = 16;
+/// Time a client has to send its whole request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A named point in time, such as "deploy started", dropped from the
+/// flamescope or by a script.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    /// Local wall-clock time, in nanoseconds since the unix epoch.
+    pub unix_nano: u64,
+    pub label: String,
+}
+
+impl Marker {
+    pub fn now(label: String) -> Self {
+        let unix_nano = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Self { unix_nano, label }
+    }
+
+    pub fn unix_secs(&self) -> u64 {
+        self.unix_nano / 1_000_000_000
+    }
+}
+
+/// Listens for `POST /markers` requests whose body is the label of a
+/// marker to drop at the time of the request:
+///
+/// ```text
+/// curl -d 'deploy started' http://localhost:4320/markers
+/// ```
+///
+/// Each connection is handled on its own thread, so that a slow client
+/// doesn't hold up the others, up to [`MAX_CONNECTIONS`] at once and for
+/// at most [`REQUEST_TIMEOUT`] each.
+pub fn spawn_server(addr: SocketAddr, event_tx: mpsc::Sender<Event>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let active = Arc::new(AtomicUsize::new(0));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                active.fetch_sub(1, Ordering::SeqCst);
+                respond(&stream, "503 Service Unavailable");
+                continue;
+            }
+            let (event_tx, active) = (event_tx.clone(), Arc::clone(&active));
+            std::thread::spawn(move || {
+                handle(stream, &event_tx);
+                active.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+    Ok(())
+}
+
+fn handle(stream: TcpStream, event_tx: &mpsc::Sender<Event>) {
+    let mut reader = BufReader::new(Deadline {
+        stream: &stream,
+        until: Instant::now() + REQUEST_TIMEOUT,
+    });
+    let status = match read_marker(&mut reader) {
+        Ok(marker) => {
+            let _ = event_tx.send(Event::Marker(marker));
+            "204 No Content"
+        }
+        Err(status) => status,
+    };
+    respond(&stream, status);
+}
+
+fn respond(mut stream: &TcpStream, status: &str) {
+    let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+    );
+}
+
+/// Reads from a stream until a deadline, however the client spaces out
+/// its bytes.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let left = self.until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Parses a request, returning the response status when it isn't a valid
+/// marker.
+fn read_marker(reader: &mut impl BufRead) -> Result<Marker, &'static str> {
+    const BAD_REQUEST: &str = "400 Bad Request";
+
+    let mut line = String::new();
+    read_line(reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = (parts.next(), parts.next().unwrap_or_default());
+    if target.split('?').next() != Some("/markers") {
+        return Err("404 Not Found");
+    }
+    if method != Some("POST") {
+        return Err("405 Method Not Allowed");
+    }
+
+    let mut length = 0;
+    for headers in 0.. {
+        line.clear();
+        read_line(reader, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return Err("431 Request Header Fields Too Large");
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse().map_err(|_| BAD_REQUEST)?;
+        }
+    }
+    if length > MAX_LABEL_BYTES {
+        return Err("413 Payload Too Large");
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|_| BAD_REQUEST)?;
+    let label = String::from_utf8(body).map_err(|_| BAD_REQUEST)?;
+    let label = label.trim();
+    if label.is_empty() {
+        return Err(BAD_REQUEST);
+    }
+    Ok(Marker::now(label.to_string()))
+}
+
+/// Reads a line of at most `MAX_LINE_BYTES`, rejecting longer or
+/// unterminated ones.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<(), &'static str> {
+    match reader.by_ref().take(MAX_LINE_BYTES).read_line(line) {
+        Ok(_) if line.ends_with('\n') => Ok(()),
+        _ => Err("400 Bad Request"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(raw: &str) -> Result<Marker, &'static str> {
+        read_marker(&mut raw.as_bytes())
+    }
+
+    #[test]
+    fn marker_requests() {
+        let marker = request(
+            "POST /markers HTTP/1.1\r\nHost: x\r\ncontent-length: 17\r\n\r\n load test begin\n",
+        )
+        .unwrap();
+        assert_eq!(marker.label, "load test begin");
+        assert!(marker.unix_nano > 0);
+
+        assert_eq!(
+            request("GET /markers HTTP/1.1\r\n\r\n"),
+            Err("405 Method Not Allowed")
+        );
+        assert_eq!(request("POST / HTTP/1.1\r\n\r\n"), Err("404 Not Found"));
+        assert_eq!(
+            request("POST /markers HTTP/1.1\r\nContent-Length: 0\r\n\r\n"),
+            Err("400 Bad Request")
+        );
+
+        let long_line = format!(
+            "POST /markers HTTP/1.1\r\nX: {}\r\n\r\n",
+            "a".repeat(10_000)
+        );
+        assert_eq!(request(&long_line), Err("400 Bad Request"));
+        let many_headers = format!("POST /markers HTTP/1.1\r\n{}\r\n", "X: a\r\n".repeat(100));
+        assert_eq!(
+            request(&many_headers),
+            Err("431 Request Header Fields Too Large")
+        );
+    }
+}
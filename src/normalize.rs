@@ -0,0 +1,200 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use clap::ValueEnum;
+use regex::Regex;
+
+use crate::demangle::{self, Demangle};
+use crate::error::{Error, Result};
+
+/// Most passes of a repeating rule over one name, as a guard against rules
+/// that never settle.
+const MAX_PASSES: usize = 16;
+
+/// Built-in rule sets, selectable on the command line or in a rules file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Preset {
+    /// Strip the `::h0123456789abcdef` hash suffix of Rust symbols.
+    RustHash,
+    /// Drop C++ template arguments, e.g. `std::vector<int>::push_back`
+    /// becomes `std::vector::push_back`.
+    CppTemplates,
+    /// Fold numbered JVM lambdas and generated proxy classes.
+    JvmLambdas,
+}
+
+impl Preset {
+    fn rules(self) -> Vec<Rule> {
+        let rule = |pattern: &str, replacement: &str, repeat| Rule {
+            regex: Regex::new(pattern).expect("valid preset regex"),
+            replacement: replacement.to_string(),
+            repeat,
+        };
+        match self {
+            Preset::RustHash => vec![rule(r"::h[0-9a-f]{16}\b", "", false)],
+            // Innermost argument lists first, until nested ones are gone.
+            Preset::CppTemplates => vec![rule(r"<[^<>()]*>", "", true)],
+            Preset::JvmLambdas => vec![
+                rule(r"\$\$Lambda(\$\d+)?(/0x[0-9a-f]+)?", "$$$$Lambda", false),
+                rule(r"\blambda\$(\w+?)\$\d+", "lambda$$${1}", false),
+                rule(r"\$Proxy\d+", "$$Proxy", false),
+                rule(
+                    r"\$\$((?:EnhancerBy|FastClassBy)\w+?)\$\$[0-9a-f]+",
+                    "$$$$${1}",
+                    false,
+                ),
+            ],
+        }
+    }
+}
+
+struct Rule {
+    regex: Regex,
+    replacement: String,
+    /// Reapply until the name stops changing.
+    repeat: bool,
+}
+
+/// Rewrites frame names so that variants of the same function (hashes,
+/// template arguments, lambda numbers) end up in one flamegraph node.
+#[derive(Default)]
+pub struct Normalizer {
+    rules: Vec<Rule>,
+}
+
+impl Normalizer {
+    pub fn from_presets(presets: &[Preset]) -> Self {
+        Self {
+            rules: presets.iter().flat_map(|p| p.rules()).collect(),
+        }
+    }
+
+    /// Appends the rules of a file. Each non-empty line is either
+    /// `preset <name>` or `<regex> => <replacement>`, where the replacement
+    /// may refer to capture groups as `$1` or `${name}`; `#` starts a
+    /// comment line.
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(path)?;
+        let rules = parse(&text).map_err(|(line, message)| Error::Rules {
+            path: path.to_path_buf(),
+            line,
+            message,
+        })?;
+        self.rules.extend(rules);
+        Ok(())
+    }
+
+    /// Rewrites a name, demangling it first so that rules match the names
+    /// as displayed.
+    pub fn apply<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.rules.is_empty() {
+            return Cow::Borrowed(name);
+        }
+        let mut name = demangle::symbol(name, Demangle::Full);
+        for rule in &self.rules {
+            for _ in 0..if rule.repeat { MAX_PASSES } else { 1 } {
+                match rule.regex.replace_all(&name, rule.replacement.as_str()) {
+                    Cow::Owned(replaced) if replaced != name => name = Cow::Owned(replaced),
+                    _ => break,
+                }
+            }
+        }
+        name
+    }
+}
+
+fn parse(text: &str) -> std::result::Result<Vec<Rule>, (usize, String)> {
+    let mut rules = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err = |message: String| (i + 1, message);
+        if let Some(name) = line.strip_prefix("preset ") {
+            let preset = Preset::from_str(name.trim(), true).map_err(err)?;
+            rules.extend(preset.rules());
+            continue;
+        }
+        let Some((pattern, replacement)) = line.split_once(" => ") else {
+            return Err(err("expected `<regex> => <replacement>`".to_string()));
+        };
+        rules.push(Rule {
+            regex: Regex::new(pattern.trim()).map_err(|e| err(e.to_string()))?,
+            replacement: replacement.trim().to_string(),
+            repeat: false,
+        });
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        let n =
+            Normalizer::from_presets(&[Preset::RustHash, Preset::CppTemplates, Preset::JvmLambdas]);
+        assert_eq!(
+            n.apply("core::ptr::drop_in_place::h1a2b3c4d5e6f7a8b"),
+            "core::ptr::drop_in_place"
+        );
+        assert_eq!(
+            n.apply("std::map<int, std::vector<char>>::find(int const&)"),
+            "std::map::find(int const&)"
+        );
+        assert_eq!(
+            n.apply("com.app.Main$$Lambda$42/0x0000000800c0b448.apply"),
+            "com.app.Main$$Lambda.apply"
+        );
+        assert_eq!(
+            n.apply("com.app.Main.lambda$run$3"),
+            "com.app.Main.lambda$run"
+        );
+        assert_eq!(
+            n.apply("com.app.Service$$EnhancerBySpringCGLIB$$8f2e1a.call"),
+            "com.app.Service$$EnhancerBySpringCGLIB.call"
+        );
+        assert!(matches!(n.apply("main"), Cow::Borrowed("main")));
+    }
+
+    #[test]
+    fn presets_match_mangled_names() {
+        let n = Normalizer::from_presets(&[Preset::RustHash, Preset::CppTemplates]);
+        assert_eq!(
+            n.apply("_ZN4core3ptr13drop_in_place17h0123456789abcdefE"),
+            "core::ptr::drop_in_place"
+        );
+        // std::vector<int>::push_back(int const&)
+        assert_eq!(
+            n.apply("_ZNSt6vectorIiE9push_backERKi"),
+            "std::vector::push_back(int const&)"
+        );
+        assert!(matches!(
+            Normalizer::default().apply("_ZN3foo3barEi"),
+            Cow::Borrowed("_ZN3foo3barEi")
+        ));
+    }
+
+    #[test]
+    fn parse_rules_file() {
+        let rules = parse(
+            "# comment\n\
+             preset rust-hash\n\
+             ^tokio::runtime::.* => tokio::runtime\n\
+             (\\w+)_v\\d+ => $1\n",
+        )
+        .unwrap();
+        let n = Normalizer { rules };
+        assert_eq!(
+            n.apply("tokio::runtime::park::h0123456789abcdef"),
+            "tokio::runtime"
+        );
+        assert_eq!(n.apply("decode_v2"), "decode");
+
+        assert_eq!(parse("a => b\nnot a rule").err().unwrap().0, 2);
+        assert_eq!(parse("preset nope").err().unwrap().0, 1);
+        assert!(parse("( => x").is_err());
+    }
+}
@@ -8,9 +8,14 @@ pub use symblib::fileid::FileId;
 use zerocopy::byteorder::{BigEndian, U16, U32, U64, U128};
 use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
 
+use crate::flamegraph::{FlameGraph, FlameNode, take, take_u32};
+use crate::markers::Marker;
 use crate::symbolizer::{FileSym, SymRange};
 
 const NONE_REF: u32 = u32::MAX;
+/// Version 2 added the frame type of every node, version 3 its module,
+/// version 4 the markers after the tree.
+const SNAPSHOT_FORMAT: u8 = 4;
 
 /// Big-endian key for the ranges LSM partition.
 ///
@@ -108,17 +113,87 @@ pub struct ExecutableInfo {
     pub num_ranges: u32,
 }
 
+/// A named copy of the flamegraph taken during a session.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub name: String,
+    pub graph: FlameGraph,
+    pub samples: u64,
+    /// Unix seconds of the first and last profile merged into `graph`.
+    pub start_secs: u64,
+    pub end_secs: u64,
+    /// Markers dropped between the start and the end.
+    pub markers: Vec<Marker>,
+}
+
+impl Snapshot {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![SNAPSHOT_FORMAT];
+        buf.extend_from_slice(&self.samples.to_be_bytes());
+        buf.extend_from_slice(&self.start_secs.to_be_bytes());
+        buf.extend_from_slice(&self.end_secs.to_be_bytes());
+        self.graph.root.encode_into(&mut buf);
+        buf.extend_from_slice(&(self.markers.len() as u32).to_be_bytes());
+        for marker in &self.markers {
+            buf.extend_from_slice(&marker.unix_nano.to_be_bytes());
+            buf.extend_from_slice(&(marker.label.len() as u32).to_be_bytes());
+            buf.extend_from_slice(marker.label.as_bytes());
+        }
+        buf
+    }
+
+    fn decode(name: String, vb: &[u8]) -> Option<Self> {
+        let (&format, rest) = vb.split_first()?;
+        if !(1..=SNAPSHOT_FORMAT).contains(&format) || rest.len() < 24 {
+            return None;
+        }
+        let (header, mut tree) = rest.split_at(24);
+        let field = |i: usize| u64::from_be_bytes(header[i * 8..(i + 1) * 8].try_into().unwrap());
+        let root = FlameNode::decode(&mut tree, format)?;
+        let markers = if format >= 4 {
+            decode_markers(&mut tree)?
+        } else {
+            Vec::new()
+        };
+        if !tree.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name,
+            graph: FlameGraph { root },
+            samples: field(0),
+            start_secs: field(1),
+            end_secs: field(2),
+            markers,
+        })
+    }
+}
+
+fn decode_markers(bytes: &mut &[u8]) -> Option<Vec<Marker>> {
+    let count = take_u32(bytes)?;
+    let mut markers = Vec::new();
+    for _ in 0..count {
+        let unix_nano = u64::from_be_bytes(take(bytes, 8)?.try_into().ok()?);
+        let len = take_u32(bytes)? as usize;
+        let label = String::from_utf8_lossy(take(bytes, len)?).into_owned();
+        markers.push(Marker { unix_nano, label });
+    }
+    Some(markers)
+}
+
 /// Persistent symbol store backed by fjall (LSM-tree).
 ///
-/// Three partitions:
+/// Four partitions:
 ///   - **ranges**: `RangeKey -> RangeValue` (fixed 26-byte key, 20-byte value)
 ///   - **strings**: `StringKey -> raw UTF-8` (fixed 20-byte key, variable value)
 ///   - **files**: `U128<BE> -> num_ranges(4) + filename` (executable metadata)
+///   - **snapshots**: `name -> format(1) + samples(8) + start(8) + end(8) + tree + markers`
 pub struct SymbolStore {
     db: Database,
     ranges: Keyspace,
     strings: Keyspace,
     files: Keyspace,
+    snapshots: Keyspace,
     basename_index: RwLock<HashMap<String, FileId>>,
 }
 
@@ -135,12 +210,14 @@ impl SymbolStore {
         let ranges = db.keyspace("ranges", KeyspaceCreateOptions::default)?;
         let strings = db.keyspace("strings", KeyspaceCreateOptions::default)?;
         let files = db.keyspace("files", KeyspaceCreateOptions::default)?;
+        let snapshots = db.keyspace("snapshots", KeyspaceCreateOptions::default)?;
 
         let store = Self {
             db,
             ranges,
             strings,
             files,
+            snapshots,
             basename_index: RwLock::new(HashMap::new()),
         };
 
@@ -290,6 +367,31 @@ impl SymbolStore {
 
         Ok(())
     }
+
+    /// Persist a flamegraph snapshot, replacing any with the same name.
+    pub fn save_snapshot(&self, snapshot: &Snapshot) -> crate::Result<()> {
+        self.snapshots
+            .insert(snapshot.name.as_bytes(), snapshot.encode())?;
+        Ok(())
+    }
+
+    /// List all stored snapshots, skipping entries in an unknown format.
+    pub fn list_snapshots(&self) -> crate::Result<Vec<Snapshot>> {
+        let mut result = Vec::new();
+        for guard in self.snapshots.range::<Vec<u8>, _>(..) {
+            let (kb, vb) = guard.into_inner()?;
+            let name = String::from_utf8_lossy(&kb).into_owned();
+            if let Some(snapshot) = Snapshot::decode(name, &vb) {
+                result.push(snapshot);
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn remove_snapshot(&self, name: &str) -> crate::Result<()> {
+        self.snapshots.remove(name.as_bytes())?;
+        Ok(())
+    }
 }
 
 fn parse_file_meta(kb: &[u8], vb: &[u8]) -> Option<ExecutableInfo> {
@@ -309,3 +411,42 @@ fn parse_file_meta(kb: &[u8], vb: &[u8]) -> Option<ExecutableInfo> {
 fn basename_of(path: &str) -> String {
     path.rsplit('/').next().unwrap_or(path).to_owned()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_roundtrip_keeps_markers() {
+        let mut graph = FlameGraph::new();
+        graph.root.self_value = 7;
+        let snapshot = Snapshot {
+            name: "deploy".to_string(),
+            graph,
+            samples: 7,
+            start_secs: 100,
+            end_secs: 160,
+            markers: vec![Marker {
+                unix_nano: 120_000_000_000,
+                label: "deploy started".to_string(),
+            }],
+        };
+        let decoded = Snapshot::decode("deploy".to_string(), &snapshot.encode()).unwrap();
+        assert_eq!(decoded.graph.root.total_value, 7);
+        assert_eq!((decoded.start_secs, decoded.end_secs), (100, 160));
+        assert_eq!(decoded.markers, snapshot.markers);
+
+        // Format 3 snapshots end with the tree.
+        let mut v3 = snapshot.encode();
+        v3[0] = 3;
+        v3.truncate(v3.len() - (4 + 8 + 4 + "deploy started".len()));
+        let decoded = Snapshot::decode("deploy".to_string(), &v3).unwrap();
+        assert!(decoded.markers.is_empty());
+
+        // Trailing bytes mean the value is corrupt, whatever the format.
+        for mut bytes in [snapshot.encode(), v3] {
+            bytes.push(0);
+            assert!(Snapshot::decode("deploy".to_string(), &bytes).is_none());
+        }
+    }
+}
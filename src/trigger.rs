@@ -0,0 +1,406 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::flamegraph::{FlameGraph, Stack, Timestamps};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+/// How far the newest sample may get ahead of a resource that stopped
+/// reporting before seconds are judged without it.
+const GRACE_SECS: u64 = 10;
+
+/// What a trigger watches, judged one second of samples at a time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    /// More than `above` samples per second, of one resource or of all.
+    Rate {
+        resource: Option<String>,
+        above: u64,
+    },
+    /// More than `above_pct` percent of the samples have a frame whose name
+    /// contains `function`.
+    Function { function: String, above_pct: f64 },
+}
+
+/// A condition that captures the flamegraph once it held for `for_secs`
+/// consecutive seconds, written as `rate>N`, `rate:RESOURCE>N` or
+/// `function:NAME>P%`, optionally followed by ` for SECSs`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trigger {
+    /// The trigger as written, naming its captures.
+    pub spec: String,
+    pub condition: Condition,
+    pub for_secs: u64,
+}
+
+impl FromStr for Trigger {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let err = |message: &str| Error::Trigger {
+            spec: spec.to_string(),
+            message: message.to_string(),
+        };
+        let (condition, for_secs) = match spec.split_once(" for ") {
+            Some((condition, secs)) => {
+                let secs = secs.trim();
+                let secs = secs.strip_suffix('s').unwrap_or(secs).parse().ok();
+                let secs = secs
+                    .filter(|&secs| secs > 0)
+                    .ok_or_else(|| err("expected seconds like `10s` after `for`"))?;
+                (condition, secs)
+            }
+            None => (spec, 1),
+        };
+        let (subject, threshold) = condition
+            .split_once('>')
+            .ok_or_else(|| err("expected `>` and a threshold"))?;
+        let (kind, target) = match subject.trim().split_once(':') {
+            Some((kind, target)) => (kind, Some(target.trim()).filter(|t| !t.is_empty())),
+            None => (subject.trim(), None),
+        };
+        let threshold = threshold.trim();
+        let condition = match kind {
+            "rate" => Condition::Rate {
+                resource: target.map(str::to_string),
+                above: threshold
+                    .parse()
+                    .map_err(|_| err("expected samples per second after `>`"))?,
+            },
+            "function" => Condition::Function {
+                function: target
+                    .ok_or_else(|| err("expected `function:NAME`"))?
+                    .to_string(),
+                above_pct: threshold
+                    .strip_suffix('%')
+                    .and_then(|pct| pct.trim().parse().ok())
+                    .filter(|pct| (0.0..100.0).contains(pct))
+                    .ok_or_else(|| err("expected a percentage like `20%` after `>`"))?,
+            },
+            _ => return Err(err("expected `rate` or `function:NAME`")),
+        };
+        Ok(Self {
+            spec: spec.to_string(),
+            condition,
+            for_secs,
+        })
+    }
+}
+
+impl Condition {
+    fn holds(&self, second: Option<&HashMap<String, Vec<(u64, Stack)>>>) -> bool {
+        let Some(second) = second else {
+            return false;
+        };
+        match self {
+            Condition::Rate { resource, above } => {
+                let weight = |stacks: &Vec<(u64, Stack)>| stacks.iter().map(|(w, _)| w).sum();
+                let samples: u64 = match resource {
+                    Some(resource) => second.get(resource).map_or(0, weight),
+                    None => second.values().map(weight).sum(),
+                };
+                samples > *above
+            }
+            Condition::Function {
+                function,
+                above_pct,
+            } => {
+                let (mut total, mut hits) = (0, 0);
+                for (weight, stack) in second.values().flatten() {
+                    total += weight;
+                    if stack.iter().any(|f| f.name.contains(function.as_str())) {
+                        hits += weight;
+                    }
+                }
+                total > 0 && hits as f64 * 100.0 > above_pct * total as f64
+            }
+        }
+    }
+
+    /// Resource whose samples make up the captured flamegraphs, all of them
+    /// without one.
+    fn resource(&self) -> Option<&str> {
+        match self {
+            Condition::Rate { resource, .. } => resource.as_deref(),
+            Condition::Function { .. } => None,
+        }
+    }
+}
+
+struct Watch {
+    trigger: Trigger,
+    /// First second of the current run of seconds the condition held in.
+    streak_start: Option<u64>,
+    /// Whether the current run already fired; the trigger re-arms once the
+    /// condition stops holding.
+    fired: bool,
+    /// Latest second judged.
+    judged_secs: Option<u64>,
+}
+
+/// Flamegraphs saved when a trigger fires.
+pub struct Capture {
+    pub spec: String,
+    /// Unix seconds of the triggering window, end-exclusive. The baseline
+    /// window has the same length and ends where it starts.
+    pub start_secs: u64,
+    pub end_secs: u64,
+    pub window: (FlameGraph, u64),
+    pub baseline: (FlameGraph, u64),
+}
+
+/// Judges the triggers against the samples by their own timestamps. A
+/// second is judged once every resource the trigger looks at sent samples
+/// of a later second, or fell `GRACE_SECS` behind the newest sample.
+#[derive(Default)]
+pub struct Watcher {
+    watches: Vec<Watch>,
+    /// Weighted samples by unix second and resource, covering the longest
+    /// triggering window and its baseline.
+    seconds: BTreeMap<u64, HashMap<String, Vec<(u64, Stack)>>>,
+    /// Newest second with samples of every resource.
+    newest: HashMap<String, u64>,
+    pub captures: usize,
+    /// Trigger and start of the window of the latest capture.
+    pub last_capture: Option<(String, u64)>,
+}
+
+impl Watcher {
+    pub fn new(triggers: Vec<Trigger>) -> Self {
+        Self {
+            watches: triggers
+                .into_iter()
+                .map(|trigger| Watch {
+                    trigger,
+                    streak_start: None,
+                    fired: false,
+                    judged_secs: None,
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds the samples of a profile update, returning the captures of the
+    /// triggers that fired.
+    pub fn record(&mut self, timestamps: &Timestamps) -> Vec<Capture> {
+        if self.watches.is_empty() {
+            return Vec::new();
+        }
+        for (resource, samples) in timestamps {
+            for (ts, weight, stack) in samples.threads.values().flatten() {
+                let secs = ts / NS_PER_SEC;
+                self.seconds
+                    .entry(secs)
+                    .or_default()
+                    .entry(resource.clone())
+                    .or_default()
+                    .push((*weight, Stack::clone(stack)));
+                let newest = self.newest.entry(resource.clone()).or_insert(secs);
+                *newest = (*newest).max(secs);
+            }
+        }
+        let Some(&newest) = self.newest.values().max() else {
+            return Vec::new();
+        };
+
+        let mut captures = Vec::new();
+        for i in 0..self.watches.len() {
+            let watch = &self.watches[i];
+            let complete = self.complete_before(watch.trigger.condition.resource(), newest);
+            let next = watch.judged_secs.map_or(0, |secs| secs + 1);
+            let pending: Vec<u64> = self
+                .seconds
+                .range(next..complete)
+                .map(|(&s, _)| s)
+                .collect();
+            for secs in pending {
+                let watch = &mut self.watches[i];
+                // Seconds without samples break every run.
+                if watch.judged_secs.is_some_and(|judged| judged + 1 < secs) {
+                    watch.streak_start = None;
+                    watch.fired = false;
+                }
+                captures.extend(self.judge(i, secs));
+                self.watches[i].judged_secs = Some(secs);
+            }
+        }
+
+        // Later windows and their baselines start after this.
+        let next = self
+            .watches
+            .iter()
+            .map(|w| w.judged_secs.map_or(0, |s| s + 1))
+            .min();
+        let longest = self.watches.iter().map(|w| w.trigger.for_secs).max();
+        self.seconds = self
+            .seconds
+            .split_off(&next.unwrap_or(0).saturating_sub(2 * longest.unwrap_or(1)));
+
+        if let Some(capture) = captures.iter().max_by_key(|c| c.start_secs) {
+            self.captures += captures.len();
+            self.last_capture = Some((capture.spec.clone(), capture.start_secs));
+        }
+        captures
+    }
+
+    /// Second before which every second has all its samples of `resource`,
+    /// or of every resource without one.
+    fn complete_before(&self, resource: Option<&str>, newest: u64) -> u64 {
+        let stalled = newest.saturating_sub(GRACE_SECS);
+        self.newest
+            .iter()
+            .filter(|(name, _)| resource.is_none_or(|r| r == name.as_str()))
+            .map(|(_, &secs)| secs.max(stalled))
+            .min()
+            .unwrap_or(stalled)
+    }
+
+    fn judge(&mut self, i: usize, secs: u64) -> Option<Capture> {
+        let watch = &mut self.watches[i];
+        if !watch.trigger.condition.holds(self.seconds.get(&secs)) {
+            watch.streak_start = None;
+            watch.fired = false;
+            return None;
+        }
+        let start = *watch.streak_start.get_or_insert(secs);
+        let end = secs + 1;
+        if watch.fired || end - start < watch.trigger.for_secs {
+            return None;
+        }
+        watch.fired = true;
+
+        let watch = &self.watches[i];
+        let resource = watch.trigger.condition.resource();
+        Some(Capture {
+            spec: watch.trigger.spec.clone(),
+            start_secs: start,
+            end_secs: end,
+            window: self.graph(resource, start..end),
+            baseline: self.graph(resource, start.saturating_sub(end - start)..start),
+        })
+    }
+
+    /// Flamegraph and sample count of a range of seconds.
+    fn graph(&self, resource: Option<&str>, secs: Range<u64>) -> (FlameGraph, u64) {
+        let mut graph = FlameGraph::new();
+        let mut samples = 0;
+        for second in self.seconds.range(secs).map(|(_, second)| second) {
+            for (name, stacks) in second {
+                if resource.is_some_and(|r| r != name) {
+                    continue;
+                }
+                for (weight, stack) in stacks {
+                    graph.add_stack(stack, *weight as i64);
+                    samples += weight;
+                }
+            }
+        }
+        graph.root.sort_recursive();
+        (graph, samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flamegraph::{ResourceSamples, test_stack};
+
+    /// `per_sec[i]` samples of `leaf` in second `1_000 + i` on `resource`.
+    fn timestamps(resource: &str, leaf: &str, per_sec: &[u64]) -> Timestamps {
+        let stack = &test_stack(&["worker", "main", leaf]);
+        let samples = per_sec
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &n)| {
+                let secs = (1_000 + i as u64) * NS_PER_SEC;
+                (0..n).map(move |j| (secs + j * 1_000, 1, stack.clone()))
+            })
+            .collect();
+        HashMap::from([(
+            resource.to_string(),
+            ResourceSamples {
+                threads: HashMap::from([("worker".to_string(), samples)]),
+                approximated: false,
+            },
+        )])
+    }
+
+    #[test]
+    fn trigger_specs() {
+        let trigger: Trigger = "rate:api > 500 for 10s".parse().unwrap();
+        assert_eq!(
+            trigger.condition,
+            Condition::Rate {
+                resource: Some("api".to_string()),
+                above: 500
+            }
+        );
+        assert_eq!(trigger.for_secs, 10);
+
+        let trigger: Trigger = "function:parse_json>20%".parse().unwrap();
+        assert_eq!(
+            trigger.condition,
+            Condition::Function {
+                function: "parse_json".to_string(),
+                above_pct: 20.0
+            }
+        );
+        assert_eq!(trigger.for_secs, 1);
+
+        assert!("rate>fast".parse::<Trigger>().is_err());
+        assert!("function:x>20".parse::<Trigger>().is_err());
+        assert!("cpu>5".parse::<Trigger>().is_err());
+        assert!("rate>5 for 0s".parse::<Trigger>().is_err());
+    }
+
+    #[test]
+    fn fires_once_per_spike_with_baseline() {
+        let mut watcher = Watcher::new(vec!["rate:api>3 for 2s".parse().unwrap()]);
+        // Seconds 1002 and 1003 exceed the rate; 1004 is only judged once a
+        // later second arrives.
+        assert!(
+            watcher
+                .record(&timestamps("api", "a", &[2, 1, 5]))
+                .is_empty()
+        );
+        let captures = watcher.record(&timestamps("api", "b", &[0, 0, 0, 4, 4, 4]));
+        assert_eq!(captures.len(), 1);
+        let capture = &captures[0];
+        assert_eq!((capture.start_secs, capture.end_secs), (1_002, 1_004));
+        assert_eq!(capture.window.1, 9);
+        assert_eq!(capture.baseline.1, 3);
+        assert_eq!(watcher.captures, 1);
+
+        // Still the same spike; it fires again only after it calmed down.
+        let calm = [0, 0, 0, 0, 0, 0, 1, 4, 4, 4];
+        assert_eq!(watcher.record(&timestamps("api", "c", &calm)).len(), 1);
+        assert_eq!(
+            watcher.last_capture,
+            Some(("rate:api>3 for 2s".to_string(), 1_007))
+        );
+    }
+
+    #[test]
+    fn waits_for_slower_resources() {
+        let mut watcher = Watcher::new(vec!["rate>3".parse().unwrap()]);
+        assert!(watcher.record(&timestamps("batch", "a", &[1])).is_empty());
+        // Second 1001 has only half of its samples until batch catches up.
+        assert!(
+            watcher
+                .record(&timestamps("api", "a", &[1, 2, 2]))
+                .is_empty()
+        );
+        let captures = watcher.record(&timestamps("batch", "a", &[0, 2, 2]));
+        assert_eq!(captures.len(), 1);
+        assert_eq!((captures[0].start_secs, captures[0].window.1), (1_001, 4));
+
+        // Once batch fell far enough behind, seconds are judged without it.
+        let mut late = [0; 21];
+        late[20] = 1;
+        assert!(watcher.record(&timestamps("api", "a", &late)).is_empty());
+        assert_eq!(watcher.watches[0].judged_secs, Some(1_002));
+        assert_eq!(watcher.complete_before(None, 1_020), 1_010);
+    }
+}
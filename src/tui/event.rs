@@ -1,5 +1,6 @@
-use ratatui::crossterm::event::{self, Event as CrosstermEvent, KeyEvent, KeyEventKind};
-use std::collections::HashMap;
+use ratatui::crossterm::event::{
+    self, Event as CrosstermEvent, KeyEvent, KeyEventKind, MouseEvent,
+};
 use std::sync::{
     Arc,
     atomic::{AtomicBool, Ordering},
@@ -9,19 +10,24 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::error::Result;
-use crate::flamegraph::FlameGraph;
+use crate::flamegraph::{FlameGraph, Timestamps};
+use crate::markers::Marker;
 use crate::storage::ExecutableInfo;
 
 pub enum Event {
     Tick,
     Key(KeyEvent),
+    Mouse(MouseEvent),
     Resize,
     ProfileUpdate {
         flamegraph: FlameGraph,
         samples: u64,
-        timestamps: HashMap<String, Vec<u64>>,
+        /// Sampled stacks with a timestamp.
+        timestamps: Timestamps,
     },
     MappingsDiscovered(Vec<String>),
+    /// A marker posted to the marker endpoint.
+    Marker(Marker),
     SymbolsLoaded {
         target_name: String,
         info: Result<ExecutableInfo>,
@@ -30,6 +36,14 @@ pub enum Event {
         name: String,
         error: Option<crate::error::Error>,
     },
+    SnapshotSaved {
+        name: String,
+        error: Option<crate::error::Error>,
+    },
+    SnapshotRemoved {
+        name: String,
+        error: Option<crate::error::Error>,
+    },
 }
 
 #[allow(dead_code)]
@@ -62,6 +76,9 @@ impl EventHandler {
                             CrosstermEvent::Key(e) if e.kind == KeyEventKind::Press => {
                                 let _ = sender.send(Event::Key(e));
                             }
+                            CrosstermEvent::Mouse(e) => {
+                                let _ = sender.send(Event::Mouse(e));
+                            }
                             CrosstermEvent::Resize(_, _) => {
                                 let _ = sender.send(Event::Resize);
                             }
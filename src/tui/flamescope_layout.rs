@@ -1,34 +1,37 @@
 use ratatui::layout::Rect;
 
-use crate::tui::state::FlamescopeTab;
-
-const GRID_COLS: usize = 30;
-const GRID_ROWS: usize = FlamescopeTab::ROWS;
-const LABEL_W: u16 = 6;
+/// Width of one cell; the number of visible columns follows from the
+/// terminal width.
+const CELL_W: u16 = 2;
+const LABEL_W: u16 = 7;
 
 pub struct FlamescopeLayout {
     pub area: Rect,
     pub cell_w: u16,
     pub cell_h: u16,
     pub visible_cols: usize,
+    /// Rows that fit on screen; the grid scrolls vertically beyond that.
+    pub visible_rows: usize,
     pad_top: u16,
     pad_left: u16,
 }
 
 impl FlamescopeLayout {
-    pub fn new(area: Rect) -> Option<Self> {
-        if area.width < 10 || area.height < 2 {
+    pub fn new(area: Rect, rows: usize) -> Option<Self> {
+        if area.width < 10 || area.height < 2 || rows == 0 {
             return None;
         }
 
         let avail_h = area.height as usize;
         let avail_w = area.width.saturating_sub(LABEL_W) as usize;
 
-        let cell_h = (avail_h / GRID_ROWS).max(1) as u16;
-        let cell_w = (avail_w / GRID_COLS).max(2) as u16;
+        let cell_h = (avail_h / rows).max(1) as u16;
+        let cell_w = CELL_W;
+        let visible_rows = rows.min(avail_h / cell_h as usize);
+        let visible_cols = avail_w / cell_w as usize;
 
-        let used_h = cell_h * GRID_ROWS as u16;
-        let used_w = LABEL_W + cell_w * GRID_COLS as u16;
+        let used_h = cell_h * visible_rows as u16;
+        let used_w = LABEL_W + cell_w * visible_cols as u16;
         let pad_top = (area.height.saturating_sub(used_h)) / 2;
         let pad_left = (area.width.saturating_sub(used_w)) / 2;
 
@@ -36,30 +39,33 @@ impl FlamescopeLayout {
             area,
             cell_w,
             cell_h,
-            visible_cols: GRID_COLS,
+            visible_cols,
+            visible_rows,
             pad_top,
             pad_left,
         })
     }
 
-    pub const fn num_rows(&self) -> usize {
-        GRID_ROWS
-    }
-
-    pub fn row_y(&self, row: usize) -> u16 {
-        self.area.y + self.pad_top + row as u16 * self.cell_h
+    pub fn row_y(&self, row_offset: usize) -> u16 {
+        self.area.y + self.pad_top + row_offset as u16 * self.cell_h
     }
 
     pub fn cell_x(&self, col_offset: usize) -> u16 {
         self.area.x + self.pad_left + LABEL_W + col_offset as u16 * self.cell_w
     }
 
-    pub fn label_x(&self) -> u16 {
-        self.area.x + self.pad_left
+    /// Column and row offsets of the grid cell at `(x, y)`.
+    pub fn cell_at(&self, x: u16, y: u16) -> Option<(usize, usize)> {
+        if x >= self.right() || y >= self.bottom() {
+            return None;
+        }
+        let col_off = (x.checked_sub(self.cell_x(0))? / self.cell_w) as usize;
+        let row_off = (y.checked_sub(self.row_y(0))? / self.cell_h) as usize;
+        (col_off < self.visible_cols && row_off < self.visible_rows).then_some((col_off, row_off))
     }
 
-    pub fn ms_label(&self, row: usize) -> usize {
-        (row * 1000) / GRID_ROWS
+    pub fn label_x(&self) -> u16 {
+        self.area.x + self.pad_left
     }
 
     pub fn bottom(&self) -> u16 {
@@ -75,50 +81,73 @@ impl FlamescopeLayout {
 mod tests {
     use super::*;
 
+    const ROWS: [usize; 4] = [10, 20, 50, 100];
+
     #[test]
     fn all_rows_same_height() {
-        for h in 10u16..=120 {
-            let area = Rect::new(0, 0, 200, h);
-            let lay = FlamescopeLayout::new(area).unwrap();
-            assert!(lay.cell_h >= 1);
-            for row in 1..GRID_ROWS {
-                assert_eq!(lay.row_y(row) - lay.row_y(row - 1), lay.cell_h);
+        for rows in ROWS {
+            for h in 10u16..=120 {
+                let area = Rect::new(0, 0, 200, h);
+                let lay = FlamescopeLayout::new(area, rows).unwrap();
+                assert!(lay.cell_h >= 1);
+                for row in 1..lay.visible_rows {
+                    assert_eq!(lay.row_y(row) - lay.row_y(row - 1), lay.cell_h);
+                }
             }
         }
     }
 
     #[test]
     fn grid_stays_within_area() {
-        for h in 10u16..=120 {
-            let area = Rect::new(0, 5, 200, h);
-            let lay = FlamescopeLayout::new(area).unwrap();
-            let last_bottom = lay.row_y(GRID_ROWS - 1) + lay.cell_h;
-            assert!(last_bottom <= lay.bottom(), "height {h}: grid overflows");
+        for rows in ROWS {
+            for h in 10u16..=120 {
+                let area = Rect::new(0, 5, 200, h);
+                let lay = FlamescopeLayout::new(area, rows).unwrap();
+                let last_bottom = lay.row_y(lay.visible_rows - 1) + lay.cell_h;
+                assert!(last_bottom <= lay.bottom(), "height {h}: grid overflows");
+                assert_eq!(lay.visible_rows == rows, h as usize >= rows);
+            }
         }
     }
 
     #[test]
     fn grid_centered_vertically() {
         let area = Rect::new(0, 0, 200, 47);
-        let lay = FlamescopeLayout::new(area).unwrap();
-        let used = lay.cell_h * GRID_ROWS as u16;
+        let lay = FlamescopeLayout::new(area, 10).unwrap();
+        let used = lay.cell_h * 10;
         let leftover = 47 - used;
         assert_eq!(lay.pad_top, leftover / 2);
     }
 
     #[test]
-    fn grid_centered_horizontally() {
-        let area = Rect::new(0, 0, 200, 50);
-        let lay = FlamescopeLayout::new(area).unwrap();
-        let used_w = LABEL_W + lay.cell_w * GRID_COLS as u16;
-        let leftover = 200 - used_w;
-        assert_eq!(lay.pad_left, leftover / 2);
+    fn visible_cols_follow_width() {
+        for w in [40u16, 80, 200, 401] {
+            let area = Rect::new(0, 0, w, 50);
+            let lay = FlamescopeLayout::new(area, 10).unwrap();
+            assert_eq!(lay.visible_cols, (w - LABEL_W) as usize / CELL_W as usize);
+            let used_w = LABEL_W + lay.cell_w * lay.visible_cols as u16;
+            assert_eq!(lay.pad_left, (w - used_w) / 2);
+        }
     }
 
     #[test]
-    fn visible_cols_always_30() {
-        let area = Rect::new(0, 0, 200, 50);
-        let lay = FlamescopeLayout::new(area).unwrap();
-        assert_eq!(lay.visible_cols, 30);
+    fn cell_at_inverts_cell_positions() {
+        let area = Rect::new(3, 2, 200, 47);
+        let lay = FlamescopeLayout::new(area, 20).unwrap();
+        for row in 0..lay.visible_rows {
+            for col in [0, 7, lay.visible_cols - 1] {
+                let (x, y) = (lay.cell_x(col), lay.row_y(row));
+                assert_eq!(lay.cell_at(x, y), Some((col, row)));
+                assert_eq!(
+                    lay.cell_at(x + lay.cell_w - 1, y + lay.cell_h - 1),
+                    Some((col, row))
+                );
+            }
+        }
+        assert_eq!(lay.cell_at(lay.label_x(), lay.row_y(0)), None);
+        assert_eq!(
+            lay.cell_at(lay.cell_x(lay.visible_cols), lay.row_y(0)),
+            None
+        );
     }
 }
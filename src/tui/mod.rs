@@ -1,5 +1,6 @@
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use state::State;
 use std::{io, panic};
@@ -48,16 +49,27 @@ impl Tui {
         Ok(())
     }
 
+    /// Mouse capture stops the terminal's own text selection, hence the
+    /// toggle.
+    pub fn set_mouse_capture(&mut self, enabled: bool) -> Result<()> {
+        if enabled {
+            ratatui::crossterm::execute!(io::stderr(), EnableMouseCapture)?;
+        } else {
+            ratatui::crossterm::execute!(io::stderr(), DisableMouseCapture)?;
+        }
+        Ok(())
+    }
+
     pub fn reset() -> Result<()> {
         terminal::disable_raw_mode()?;
-        ratatui::crossterm::execute!(io::stderr(), LeaveAlternateScreen)?;
+        ratatui::crossterm::execute!(io::stderr(), DisableMouseCapture, LeaveAlternateScreen)?;
         Terminal::new(CrosstermBackend::new(io::stderr()))?.show_cursor()?;
         Ok(())
     }
 
     pub fn exit(&mut self) -> Result<()> {
         terminal::disable_raw_mode()?;
-        ratatui::crossterm::execute!(io::stderr(), LeaveAlternateScreen)?;
+        ratatui::crossterm::execute!(io::stderr(), DisableMouseCapture, LeaveAlternateScreen)?;
         self.terminal.show_cursor()?;
         self.events.stop();
         Ok(())
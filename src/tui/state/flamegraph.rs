@@ -1,7 +1,168 @@
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use super::{SearchAction, SearchOverlay};
-use crate::flamegraph::{FlameGraph, FlameNode};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
+use ratatui::style::Color;
+use regex::{Regex, RegexBuilder};
+
+use super::{Action, SearchAction, SearchOverlay};
+use crate::demangle::{self, Demangle};
+use crate::flamegraph::{
+    Butterfly, FilterKind, FlameGraph, FlameNode, FrameType, StackFilter, frame_path_at,
+};
+use crate::markers::Marker;
+use crate::storage::Snapshot;
+
+/// Entry of the snapshot list that switches back to the live graph.
+const LIVE_ENTRY: &str = "● live";
+
+/// Window lengths cycled with `w`/`W`; `None` accumulates forever.
+const WINDOW_PRESETS: &[Option<u64>] = &[None, Some(10), Some(60), Some(300)];
+/// Longest gap between two clicks on a frame that zooms into it.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// How recursive frames are folded in the displayed tree.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Recursion {
+    Expanded,
+    /// Fold `a → a`.
+    Direct,
+    /// Also fold cycles such as `a → b → a`.
+    Cycles,
+}
+
+impl Recursion {
+    fn next(self) -> Self {
+        match self {
+            Recursion::Expanded => Recursion::Direct,
+            Recursion::Direct => Recursion::Cycles,
+            Recursion::Cycles => Recursion::Expanded,
+        }
+    }
+}
+
+/// What decides the color of a frame.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    /// Palette of the top-level thread, shaded by self time.
+    Thread,
+    FrameType,
+    /// Executable or shared library, falling back to the frame type.
+    Module,
+    /// Crate, namespace or package prefix of the name.
+    Package,
+    /// Hash of the name, so a function keeps its color across sessions.
+    Name,
+}
+
+impl ColorScheme {
+    fn next(self) -> Self {
+        match self {
+            ColorScheme::Thread => ColorScheme::FrameType,
+            ColorScheme::FrameType => ColorScheme::Module,
+            ColorScheme::Module => ColorScheme::Package,
+            ColorScheme::Package => ColorScheme::Name,
+            ColorScheme::Name => ColorScheme::Thread,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorScheme::Thread => "thread",
+            ColorScheme::FrameType => "frame type",
+            ColorScheme::Module => "module",
+            ColorScheme::Package => "package",
+            ColorScheme::Name => "name hash",
+        }
+    }
+}
+
+/// Frames matching a pattern, highlighted wherever they appear.
+pub struct Highlight {
+    pub label: String,
+    regex: Regex,
+    /// Match raw frame names rather than their displayed form.
+    exact: bool,
+    /// Index paths of matches below the zoom root, in pre-order.
+    matches: Vec<Vec<usize>>,
+    pub cursor: usize,
+    /// Samples covered by the matches, counting nested matches once.
+    pub matched_value: i64,
+}
+
+impl Highlight {
+    /// Case-insensitive regex, falling back to a literal substring when
+    /// `pattern` is not a valid regex.
+    fn search(pattern: &str) -> Option<Self> {
+        if pattern.is_empty() {
+            return None;
+        }
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .or_else(|_| {
+                RegexBuilder::new(&regex::escape(pattern))
+                    .case_insensitive(true)
+                    .build()
+            })
+            .ok()?;
+        Some(Self::new(pattern.to_string(), regex, false))
+    }
+
+    /// Exact frame name.
+    fn exact(name: &str) -> Option<Self> {
+        let regex = Regex::new(&format!("^{}$", regex::escape(name))).ok()?;
+        Some(Self::new(name.to_string(), regex, true))
+    }
+
+    fn new(label: String, regex: Regex, exact: bool) -> Self {
+        Self {
+            label,
+            regex,
+            exact,
+            matches: Vec::new(),
+            cursor: 0,
+            matched_value: 0,
+        }
+    }
+
+    /// Searches match frames as displayed, so demangled names can be found.
+    pub fn is_match(&self, name: &str, mode: Demangle) -> bool {
+        if self.exact {
+            self.regex.is_match(name)
+        } else {
+            self.regex.is_match(&demangle::label(name, mode))
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.matches.len()
+    }
+}
+
+/// Flamegraph of the samples in a flamescope selection.
+pub struct TimeRange {
+    pub label: String,
+    pub samples: u64,
+    pub graph: FlameGraph,
+}
+
+/// Entries of the color legend, counted once per view.
+struct Legend {
+    /// View generation, zoom path, scheme and demangling counted for.
+    key: (u64, Vec<String>, ColorScheme, Demangle),
+    entries: Vec<(String, Color)>,
+}
+
+/// Samples merged during one second of wall-clock time.
+struct Interval {
+    start_secs: u64,
+    graph: FlameGraph,
+    profiles: u64,
+    samples: u64,
+}
 
 #[derive(Default)]
 pub struct Selection {
@@ -9,33 +170,141 @@ pub struct Selection {
     pub self_value: i64,
     pub total_value: i64,
     pub pct: f64,
+    /// Share of the same frame in the baseline, set only in diff mode.
+    pub baseline_pct: Option<f64>,
     pub depth: usize,
+    pub folded_depth: usize,
 }
 
 pub struct FlamegraphTab {
     pub graph: FlameGraph,
+    /// Derived tree shown instead of `graph` when a view mode is active.
+    view: Option<FlameGraph>,
+    /// Bumped whenever the displayed tree changes.
+    view_generation: u64,
+    legend: Option<Legend>,
+    /// Graph marked as the reference for the differential view.
+    baseline: Option<FlameGraph>,
+    baseline_view: Option<FlameGraph>,
+    /// Flamescope range the baseline was taken from, if any.
+    pub baseline_range: Option<String>,
+    pub inverted: bool,
+    pub recursion: Recursion,
+    pub demangle: Demangle,
+    pub colors: ColorScheme,
     pub frozen: bool,
     pub profiles_received: u64,
     pub samples_received: u64,
     pub scroll_y: usize,
     pub cursor_path: Vec<usize>,
     pub zoom_path: Vec<String>,
+    /// Frame under the mouse pointer, shown in the detail bar instead of
+    /// the cursor.
+    pub hover: Option<Position>,
+    last_click: Option<(Instant, Vec<usize>)>,
+    /// Where the graph was last drawn, for mouse hit testing.
+    pub graph_area: Rect,
     pub selection: Selection,
-    pub search: SearchOverlay,
+    pub thread_search: SearchOverlay,
+    pub frame_search: SearchOverlay,
+    pub highlight: Option<Highlight>,
+    /// Focus/ignore/hide filters applied when deriving the view.
+    pub filters: Vec<StackFilter>,
+    pub filter_input: SearchOverlay,
+    /// Kind of the filter being entered in `filter_input`.
+    pub filter_kind: FilterKind,
+    /// Picker of frame types to hide, or to show exclusively.
+    pub type_list: SearchOverlay,
+    /// Caller/callee split view replacing the graph while open.
+    pub butterfly: Option<Butterfly>,
+    /// Caller and callee levels scrolled past next to the function in the
+    /// butterfly view.
+    pub butterfly_scroll: [usize; 2],
+    pub snapshots: Vec<Snapshot>,
+    /// Name of the snapshot displayed instead of the live graph.
+    pub active_snapshot: Option<String>,
+    /// Flamescope range displayed instead of the live graph.
+    pub range: Option<TimeRange>,
+    pub snapshot_name: SearchOverlay,
+    pub snapshot_list: SearchOverlay,
+    pub status: Option<String>,
+    /// Unix seconds of the first and last merge still in the graph.
+    started_secs: Option<u64>,
+    updated_secs: u64,
+    /// Length of the sliding window in seconds; `None` accumulates forever.
+    pub window_secs: Option<u64>,
+    /// Window lengths cycled with `w`/`W`: the presets and any other
+    /// window set, shortest first after `None`.
+    windows: Vec<Option<u64>>,
+    /// Per-second partial graphs, oldest first. All-time mode keeps those
+    /// of the longest window, so that switching to it rebuilds the graph
+    /// from complete data.
+    intervals: VecDeque<Interval>,
+    /// Node budget for `graph`; smaller subtrees are folded into `[other]`.
+    pub max_nodes: Option<usize>,
+    /// Node count of `graph`, tracked so merges need not walk the tree.
+    nodes: usize,
+    pub pruned_nodes: u64,
+    pub pruned_samples: u64,
 }
 
 impl Default for FlamegraphTab {
     fn default() -> Self {
         Self {
             graph: FlameGraph::new(),
+            view: None,
+            view_generation: 0,
+            legend: None,
+            baseline: None,
+            baseline_view: None,
+            baseline_range: None,
+            inverted: false,
+            recursion: Recursion::Expanded,
+            demangle: Demangle::default(),
+            colors: ColorScheme::Thread,
             frozen: false,
             profiles_received: 0,
             samples_received: 0,
             scroll_y: 0,
             cursor_path: Vec::new(),
             zoom_path: Vec::new(),
+            hover: None,
+            last_click: None,
+            graph_area: Rect::default(),
             selection: Selection::default(),
-            search: SearchOverlay::default(),
+            thread_search: SearchOverlay::default(),
+            frame_search: SearchOverlay::default(),
+            highlight: None,
+            filters: Vec::new(),
+            filter_input: SearchOverlay::default(),
+            filter_kind: FilterKind::Focus,
+            type_list: SearchOverlay::default(),
+            butterfly: None,
+            butterfly_scroll: [0, 0],
+            snapshots: Vec::new(),
+            active_snapshot: None,
+            range: None,
+            snapshot_name: SearchOverlay::default(),
+            snapshot_list: SearchOverlay::default(),
+            status: None,
+            started_secs: None,
+            updated_secs: 0,
+            window_secs: None,
+            windows: WINDOW_PRESETS.to_vec(),
+            intervals: VecDeque::new(),
+            max_nodes: None,
+            nodes: 1,
+            pruned_nodes: 0,
+            pruned_samples: 0,
+        }
+    }
+}
+
+impl From<Vec<Snapshot>> for FlamegraphTab {
+    fn from(snapshots: Vec<Snapshot>) -> Self {
+        Self {
+            snapshots,
+            ..Default::default()
         }
     }
 }
@@ -45,17 +314,361 @@ impl FlamegraphTab {
         if self.frozen {
             return;
         }
-        self.graph.root.merge(new_fg.root);
+        let now = unix_secs();
+        match self.intervals.back_mut() {
+            Some(last) if last.start_secs == now => {
+                last.graph.root.merge(new_fg.root.clone());
+                last.profiles += 1;
+                last.samples += samples;
+            }
+            _ => self.intervals.push_back(Interval {
+                start_secs: now,
+                graph: new_fg.clone(),
+                profiles: 1,
+                samples,
+            }),
+        }
+        self.nodes += self.graph.root.merge(new_fg.root);
         self.graph.root.sort_recursive();
+        self.prune();
         self.profiles_received += 1;
         self.samples_received += samples;
+        self.started_secs.get_or_insert(now);
+        self.updated_secs = now;
+        if self.is_live() {
+            self.rebuild_view();
+        }
+    }
+
+    /// Folds the smallest subtrees of `graph` into `[other]` once it
+    /// exceeds the node budget, folding the intervals alike so that they
+    /// stay within the budget and expire out of the right nodes.
+    fn prune(&mut self) {
+        let Some(max_nodes) = self.max_nodes.filter(|&max| self.nodes > max) else {
+            return;
+        };
+        let (nodes, samples) = self.graph.prune_to(max_nodes);
+        self.nodes -= nodes;
+        self.pruned_nodes += nodes as u64;
+        self.pruned_samples += samples as u64;
+        for interval in &mut self.intervals {
+            interval.graph.root.fold_to(&self.graph.root);
+        }
+    }
+
+    /// Drops intervals that left the window, removing their samples from
+    /// the graph when a window is active. Returns whether the graph changed.
+    pub fn expire(&mut self) -> bool {
+        !self.frozen && self.expire_at(unix_secs())
+    }
+
+    fn expire_at(&mut self, now: u64) -> bool {
+        let (retain, subtract) = match self.window_secs {
+            Some(window) => (window, true),
+            None => (
+                self.windows.iter().flatten().max().copied().unwrap_or(0),
+                false,
+            ),
+        };
+        let mut changed = false;
+        while let Some(first) = self.intervals.front()
+            && first.start_secs + retain <= now
+        {
+            let expired = self.intervals.pop_front().unwrap();
+            if subtract {
+                self.nodes -= self.graph.root.subtract(&expired.graph.root);
+                self.profiles_received -= expired.profiles;
+                self.samples_received -= expired.samples;
+                changed = true;
+            }
+        }
+        if changed {
+            self.started_secs = self.intervals.front().map(|i| i.start_secs);
+            self.graph.root.sort_recursive();
+            if self.is_live() {
+                self.rebuild_view();
+            }
+        }
+        changed
+    }
+
+    pub fn set_window(&mut self, window_secs: Option<u64>) {
+        if let Err(pos) = self.windows.binary_search(&window_secs) {
+            self.windows.insert(pos, window_secs);
+        }
+        let was_all_time = self.window_secs.is_none();
+        self.window_secs = window_secs;
+        if was_all_time && window_secs.is_some() {
+            // The all-time graph holds data older than any interval.
+            self.graph = FlameGraph::new();
+            self.nodes = 1;
+            self.profiles_received = 0;
+            self.samples_received = 0;
+            for interval in &self.intervals {
+                self.nodes += self.graph.root.merge(interval.graph.root.clone());
+                self.profiles_received += interval.profiles;
+                self.samples_received += interval.samples;
+            }
+            self.started_secs = self.intervals.front().map(|i| i.start_secs);
+            self.graph.root.sort_recursive();
+            self.prune();
+            self.rebuild_view();
+        }
+        self.expire_at(unix_secs());
+    }
+
+    fn cycle_window(&mut self, forward: bool) {
+        let len = self.windows.len();
+        let pos = self
+            .windows
+            .iter()
+            .position(|&w| w == self.window_secs)
+            .unwrap_or(0);
+        let next = if forward {
+            (pos + 1) % len
+        } else {
+            (pos + len - 1) % len
+        };
+        self.set_window(self.windows[next]);
+    }
+
+    /// The snapshot being displayed, if any.
+    pub fn snapshot(&self) -> Option<&Snapshot> {
+        let name = self.active_snapshot.as_deref()?;
+        self.snapshots.iter().find(|s| s.name == name)
+    }
+
+    /// The graph the view is built from: a flamescope range, the active
+    /// snapshot or live data.
+    fn source(&self) -> &FlameGraph {
+        if let Some(ref range) = self.range {
+            return &range.graph;
+        }
+        self.snapshot().map_or(&self.graph, |s| &s.graph)
+    }
+
+    fn is_live(&self) -> bool {
+        self.active_snapshot.is_none() && self.range.is_none()
+    }
+
+    /// Displays the flamegraph of a flamescope range until `Esc` at the top
+    /// or another snapshot is picked.
+    pub fn show_range(&mut self, range: TimeRange) {
+        self.clear_range_baseline();
+        self.range = Some(range);
+        self.active_snapshot = None;
+        self.butterfly = None;
+        self.zoom_path.clear();
+        self.cursor_path.clear();
+        self.scroll_y = 0;
+        self.rebuild_view();
+    }
+
+    /// Shows a flamescope range diffed against another, so that stacks that
+    /// grew relative to the baseline stand out.
+    pub fn compare_range(&mut self, range: TimeRange, baseline: &TimeRange) {
+        self.show_range(range);
+        self.baseline = Some(baseline.graph.clone());
+        self.baseline_range = Some(baseline.label.clone());
+        self.rebuild_baseline_view();
+    }
+
+    /// The tree currently displayed, after applying view modes.
+    pub fn view(&self) -> &FlameGraph {
+        self.view.as_ref().unwrap_or_else(|| self.source())
+    }
+
+    pub fn overlay_active(&self) -> bool {
+        self.thread_search.active
+            || self.frame_search.active
+            || self.filter_input.active
+            || self.type_list.active
+            || self.snapshot_name.active
+            || self.snapshot_list.active
+    }
+
+    /// The baseline as displayed, when diff mode is active.
+    pub fn baseline(&self) -> Option<&FlameGraph> {
+        self.baseline_view.as_ref().or(self.baseline.as_ref())
+    }
+
+    /// The tree function statistics are counted on: the view before
+    /// inverting, since an inverted tree puts the self samples of every
+    /// stack on its outermost caller.
+    pub fn functions_graph(&self) -> Cow<'_, FlameGraph> {
+        if !self.inverted {
+            return Cow::Borrowed(self.view());
+        }
+        match self.derive(self.source(), false) {
+            Some(graph) => Cow::Owned(graph),
+            None => Cow::Borrowed(self.source()),
+        }
+    }
+
+    /// Legend entries of the zoomed view, counted by `count` only when the
+    /// view, zoom, scheme or demangling changed since the last call.
+    pub fn legend(
+        &mut self,
+        count: impl FnOnce(&FlameNode) -> Vec<(String, Color)>,
+    ) -> &[(String, Color)] {
+        let key = (
+            self.view_generation,
+            self.zoom_path.clone(),
+            self.colors,
+            self.demangle,
+        );
+        if self.legend.as_ref().is_none_or(|legend| legend.key != key) {
+            let entries = count(self.zoom_root());
+            self.legend = Some(Legend { key, entries });
+        }
+        &self.legend.as_ref().expect("legend counted above").entries
+    }
+
+    fn rebuild_view(&mut self) {
+        self.view_generation += 1;
+        self.view = self.derive(self.source(), self.inverted);
+        self.refresh_matches();
+        if let Some(name) = self.butterfly.as_ref().map(|b| b.name.clone()) {
+            self.open_butterfly(&name);
+        }
+    }
+
+    /// Built from the filtered but never inverted graph, so callers stay
+    /// callers in the inverted view.
+    fn open_butterfly(&mut self, name: &str) {
+        let butterfly = if self.filters.iter().any(|f| f.enabled) {
+            self.source().filtered(&self.filters).butterfly(name)
+        } else {
+            self.source().butterfly(name)
+        };
+        self.butterfly = Some(butterfly);
+    }
+
+    /// Scrolls the callers (`half` 0) or callees (1) of the butterfly view
+    /// one level away from the function, or back towards it, keeping at
+    /// least the outermost level in view.
+    fn scroll_butterfly(&mut self, half: usize, outward: bool) {
+        let Some(ref b) = self.butterfly else {
+            return;
+        };
+        let depth = [&b.callers, &b.callees][half].max_depth();
+        let scroll = &mut self.butterfly_scroll[half];
+        *scroll = if outward {
+            (*scroll + 1).min(depth.saturating_sub(1))
+        } else {
+            scroll.saturating_sub(1)
+        };
+    }
+
+    fn rebuild_baseline_view(&mut self) {
+        self.baseline_view = self
+            .baseline
+            .as_ref()
+            .and_then(|b| self.derive(b, self.inverted));
+    }
+
+    /// Applies the active view modes, inverting only with `invert`, or
+    /// returns `None` if the graph is displayed as is.
+    fn derive(&self, graph: &FlameGraph, invert: bool) -> Option<FlameGraph> {
+        let mut derived: Option<FlameGraph> = None;
+        if self.filters.iter().any(|f| f.enabled) {
+            derived = Some(graph.filtered(&self.filters));
+        }
+        if self.recursion != Recursion::Expanded {
+            let cycles = self.recursion == Recursion::Cycles;
+            derived = Some(derived.as_ref().unwrap_or(graph).collapse_recursion(cycles));
+        }
+        if invert {
+            derived = Some(derived.as_ref().unwrap_or(graph).inverted());
+        }
+        derived
     }
 
-    fn zoom_root(&self) -> &FlameNode { self.graph.root.follow_path(&self.zoom_path) }
+    pub fn zoom_root(&self) -> &FlameNode {
+        self.view().root.follow_path(&self.zoom_path)
+    }
 
-    pub(crate) fn handle_key(&mut self, key: KeyEvent) {
-        if self.search.active {
-            return self.handle_search_key(key);
+    /// Index path below the zoom root of the frame drawn at `pos`.
+    pub fn path_at(&self, pos: Position) -> Option<Vec<usize>> {
+        if !self.graph_area.contains(pos) {
+            return None;
+        }
+        let depth = self.scroll_y + (pos.y - self.graph_area.y) as usize;
+        frame_path_at(
+            self.zoom_root(),
+            self.graph_area.width,
+            depth,
+            pos.x - self.graph_area.x,
+        )
+    }
+
+    pub(crate) fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.butterfly.is_some() {
+            return;
+        }
+        let pos = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(path) = self.path_at(pos) else {
+                    return;
+                };
+                let double = self
+                    .last_click
+                    .take()
+                    .is_some_and(|(at, last)| at.elapsed() < DOUBLE_CLICK && last == path);
+                self.cursor_path = path;
+                if double {
+                    self.zoom_in();
+                } else {
+                    self.last_click = Some((Instant::now(), self.cursor_path.clone()));
+                }
+            }
+            MouseEventKind::ScrollDown => self.move_down(),
+            MouseEventKind::ScrollUp => self.move_up(),
+            MouseEventKind::Moved => {
+                self.hover = self.path_at(pos).is_some().then_some(pos);
+            }
+            _ => {}
+        }
+    }
+
+    /// `markers` are saved with the snapshots taken.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, markers: &[Marker]) -> Action {
+        self.hover = None;
+        if self.thread_search.active {
+            self.handle_thread_search_key(key);
+            return Action::None;
+        }
+        if self.frame_search.active {
+            self.handle_frame_search_key(key);
+            return Action::None;
+        }
+        if self.filter_input.active {
+            self.handle_filter_input_key(key);
+            return Action::None;
+        }
+        if self.type_list.active {
+            self.handle_type_list_key(key);
+            return Action::None;
+        }
+        if self.snapshot_name.active {
+            return self.handle_snapshot_name_key(key, markers);
+        }
+        if self.snapshot_list.active {
+            return self.handle_snapshot_list_key(key);
+        }
+        if self.butterfly.is_some() {
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('c') => self.butterfly = None,
+                KeyCode::Char('f') | KeyCode::Char(' ') => self.frozen = !self.frozen,
+                KeyCode::Char('C') => self.colors = self.colors.next(),
+                KeyCode::Up | KeyCode::Char('k') => self.scroll_butterfly(0, true),
+                KeyCode::Down | KeyCode::Char('j') => self.scroll_butterfly(0, false),
+                KeyCode::Char('J') => self.scroll_butterfly(1, true),
+                KeyCode::Char('K') => self.scroll_butterfly(1, false),
+                _ => {}
+            }
+            return Action::None;
         }
         match key.code {
             KeyCode::Char('f') | KeyCode::Char(' ') => self.frozen = !self.frozen,
@@ -66,30 +679,349 @@ impl FlamegraphTab {
             KeyCode::Enter => self.zoom_in(),
             KeyCode::Esc | KeyCode::Backspace => self.zoom_out(),
             KeyCode::Char('r') => self.reset(),
-            KeyCode::Char('/') => {
-                self.search.open();
-                self.refresh_search();
+            KeyCode::Char('i') => self.toggle_inverted(),
+            KeyCode::Char('R') => {
+                self.recursion = self.recursion.next();
+                self.view_changed();
+            }
+            KeyCode::Char('C') => self.colors = self.colors.next(),
+            KeyCode::Char('b') => self.mark_baseline(),
+            KeyCode::Char('B') => self.clear_baseline(),
+            KeyCode::Char('w') => self.cycle_window(true),
+            KeyCode::Char('W') => self.cycle_window(false),
+            KeyCode::Char('s') => self.snapshot_name.open(),
+            KeyCode::Char('S') => {
+                self.snapshot_list.open();
+                self.refresh_snapshot_list();
+            }
+            KeyCode::Char('t') => {
+                self.thread_search.open();
+                self.refresh_thread_search();
+            }
+            KeyCode::Char('/') => self.frame_search.open(),
+            KeyCode::Char('n') => self.jump_match(true),
+            KeyCode::Char('N') => self.jump_match(false),
+            KeyCode::Char('c') => {
+                if let Some(name) = self.cursor_function() {
+                    self.butterfly_scroll = [0, 0];
+                    self.open_butterfly(&name);
+                }
+            }
+            KeyCode::Char('F') => self.open_filter_input(FilterKind::Focus),
+            KeyCode::Char('I') => self.open_filter_input(FilterKind::Ignore),
+            KeyCode::Char('H') => self.open_filter_input(FilterKind::Hide),
+            KeyCode::Char(c @ '1'..='9') => {
+                let idx = c as usize - '1' as usize;
+                if let Some(filter) = self.filters.get_mut(idx) {
+                    filter.enabled = !filter.enabled;
+                    self.view_changed();
+                }
+            }
+            KeyCode::Char('T') => {
+                self.type_list.open();
+                self.refresh_type_list();
+            }
+            KeyCode::Char('X') => {
+                if !self.filters.is_empty() {
+                    self.filters.clear();
+                    self.view_changed();
+                }
+            }
+            _ => {}
+        };
+        Action::None
+    }
+
+    /// Opens the filter prompt, prefilled with the frame under the cursor.
+    fn open_filter_input(&mut self, kind: FilterKind) {
+        self.filter_kind = kind;
+        self.filter_input.open();
+        if let Some(name) = self.cursor_function() {
+            self.filter_input.input = regex::escape(&name);
+        }
+    }
+
+    /// Name of the frame under the cursor, or `None` on the root and thread
+    /// levels.
+    fn cursor_function(&self) -> Option<String> {
+        (self.zoom_path.len() + self.cursor_path.len() >= 2).then(|| {
+            self.zoom_root()
+                .follow_indices(&self.cursor_path)
+                .name
+                .clone()
+        })
+    }
+
+    fn handle_filter_input_key(&mut self, key: KeyEvent) {
+        if key.code != KeyCode::Enter {
+            self.filter_input.handle_key(key);
+            return;
+        }
+        let pattern = std::mem::take(&mut self.filter_input.input);
+        self.filter_input.close();
+        if pattern.is_empty() {
+            return;
+        }
+        match StackFilter::new(self.filter_kind, &pattern) {
+            Ok(filter) => {
+                self.filters.push(filter);
+                self.view_changed();
+            }
+            Err(e) => self.status = Some(format!("Invalid filter regex: {e}")),
+        }
+    }
+
+    fn handle_type_list_key(&mut self, key: KeyEvent) {
+        match self.type_list.handle_key(key) {
+            SearchAction::Selected(Some(entry)) => {
+                let Some((verb, label)) = entry.split_once(' ') else {
+                    return;
+                };
+                if let Some(&t) = FrameType::ALL.iter().find(|t| t.label() == label) {
+                    self.filters
+                        .push(StackFilter::frame_type(t, verb == "only"));
+                    self.view_changed();
+                }
+            }
+            SearchAction::Refresh => self.refresh_type_list(),
+            _ => {}
+        }
+    }
+
+    fn refresh_type_list(&mut self) {
+        let query = self.type_list.input.to_lowercase();
+        self.type_list.matches = self
+            .source()
+            .frame_types()
+            .into_iter()
+            .flat_map(|t| [format!("hide {}", t.label()), format!("only {}", t.label())])
+            .filter(|entry| query.is_empty() || entry.to_lowercase().contains(&query))
+            .collect();
+    }
+
+    /// Rebuilds both trees after a filter or fold setting changed.
+    fn view_changed(&mut self) {
+        self.cursor_path.clear();
+        self.scroll_y = 0;
+        self.rebuild_baseline_view();
+        self.rebuild_view();
+    }
+
+    pub fn cycle_demangle(&mut self) {
+        self.demangle = self.demangle.next();
+        self.refresh_matches();
+    }
+
+    /// Highlights every frame named exactly `name`.
+    pub fn highlight_function(&mut self, name: &str) {
+        self.highlight = Highlight::exact(name);
+        self.refresh_matches();
+    }
+
+    fn handle_frame_search_key(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Enter {
+            self.frame_search.close();
+            self.jump_match(true);
+            return;
+        }
+        match self.frame_search.handle_key(key) {
+            SearchAction::Refresh => {
+                self.highlight = Highlight::search(&self.frame_search.input);
+                self.refresh_matches();
             }
+            SearchAction::Closed => self.highlight = None,
             _ => {}
+        }
+    }
+
+    /// Recomputes match positions after the view or zoom changed.
+    fn refresh_matches(&mut self) {
+        let Some(highlight) = self.highlight.as_ref() else {
+            return;
+        };
+        let (matches, value) = self
+            .zoom_root()
+            .find_matches(|name| highlight.is_match(name, self.demangle));
+        if let Some(highlight) = self.highlight.as_mut() {
+            highlight.cursor = highlight.cursor.min(matches.len().saturating_sub(1));
+            highlight.matches = matches;
+            highlight.matched_value = value;
+        }
+    }
+
+    /// Moves the cursor to the next or previous match. The first jump after
+    /// a new search lands on the first match.
+    fn jump_match(&mut self, forward: bool) {
+        let Some(highlight) = self.highlight.as_mut() else {
+            return;
         };
+        let len = highlight.matches.len();
+        if len == 0 {
+            return;
+        }
+        let on_match = highlight.matches.get(highlight.cursor) == Some(&self.cursor_path);
+        if on_match {
+            highlight.cursor = if forward {
+                (highlight.cursor + 1) % len
+            } else {
+                (highlight.cursor + len - 1) % len
+            };
+        }
+        self.cursor_path = highlight.matches[highlight.cursor].clone();
     }
 
-    fn handle_search_key(&mut self, key: KeyEvent) {
-        match self.search.handle_key(key) {
+    fn handle_snapshot_name_key(&mut self, key: KeyEvent, markers: &[Marker]) -> Action {
+        if key.code != KeyCode::Enter {
+            self.snapshot_name.handle_key(key);
+            return Action::None;
+        }
+        let name = self.snapshot_name.input.trim().to_string();
+        self.snapshot_name.close();
+        if name.is_empty() || name == LIVE_ENTRY {
+            return Action::None;
+        }
+        Action::SaveSnapshot(self.take_snapshot(name, markers))
+    }
+
+    fn handle_snapshot_list_key(&mut self, key: KeyEvent) -> Action {
+        if key.code == KeyCode::Delete {
+            let Some(name) = self
+                .snapshot_list
+                .matches
+                .get(self.snapshot_list.cursor)
+                .filter(|name| *name != LIVE_ENTRY)
+                .cloned()
+            else {
+                return Action::None;
+            };
+            self.remove_snapshot(&name);
+            self.status = Some(format!("Removing snapshot {name}"));
+            return Action::RemoveSnapshot(name);
+        }
+        match self.snapshot_list.handle_key(key) {
             SearchAction::Selected(Some(name)) => {
-                self.zoom_path = vec![name];
-                self.cursor_path.clear();
-                self.scroll_y = 0;
+                self.select_snapshot((name != LIVE_ENTRY).then_some(name));
+            }
+            SearchAction::Refresh => self.refresh_snapshot_list(),
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn refresh_snapshot_list(&mut self) {
+        let query = self.snapshot_list.input.to_lowercase();
+        self.snapshot_list.matches = std::iter::once(LIVE_ENTRY.to_string())
+            .chain(
+                self.snapshots
+                    .iter()
+                    .filter(|s| query.is_empty() || s.name.to_lowercase().contains(&query))
+                    .map(|s| s.name.clone()),
+            )
+            .collect();
+    }
+
+    /// Copies the live graph into a named snapshot, with the markers
+    /// dropped while it was recorded.
+    fn take_snapshot(&mut self, name: String, markers: &[Marker]) -> Snapshot {
+        let now = unix_secs();
+        let start_secs = self.started_secs.unwrap_or(now);
+        let end_secs = if self.started_secs.is_some() {
+            self.updated_secs
+        } else {
+            now
+        };
+        let snapshot = Snapshot {
+            name: name.clone(),
+            graph: self.graph.clone(),
+            samples: self.samples_received,
+            start_secs,
+            end_secs,
+            markers: markers
+                .iter()
+                .filter(|m| (start_secs..=end_secs).contains(&m.unix_secs()))
+                .cloned()
+                .collect(),
+        };
+        self.add_snapshot(snapshot.clone());
+        self.status = Some(format!("Saving snapshot {name}"));
+        snapshot
+    }
+
+    /// Lists a snapshot, replacing any snapshot with the same name.
+    pub fn add_snapshot(&mut self, snapshot: Snapshot) {
+        let name = snapshot.name.clone();
+        match self.snapshots.iter_mut().find(|s| s.name == name) {
+            Some(existing) => *existing = snapshot,
+            None => {
+                let pos = self.snapshots.partition_point(|s| s.name < name);
+                self.snapshots.insert(pos, snapshot);
             }
-            SearchAction::Refresh => self.refresh_search(),
+        }
+        if self.active_snapshot.as_ref() == Some(&name) {
+            self.rebuild_view();
+        }
+    }
+
+    /// Unlists a snapshot, going back to live data if it was shown.
+    pub fn remove_snapshot(&mut self, name: &str) {
+        self.snapshots.retain(|s| s.name != name);
+        if self.active_snapshot.as_deref() == Some(name) {
+            self.select_snapshot(None);
+        }
+        self.refresh_snapshot_list();
+        self.snapshot_list.cursor = self
+            .snapshot_list
+            .cursor
+            .min(self.snapshot_list.matches.len().saturating_sub(1));
+    }
+
+    fn select_snapshot(&mut self, name: Option<String>) {
+        self.clear_range_baseline();
+        self.range = None;
+        self.active_snapshot = name;
+        self.zoom_path.clear();
+        self.cursor_path.clear();
+        self.scroll_y = 0;
+        self.rebuild_view();
+    }
+
+    pub fn handle_snapshot_saved(&mut self, name: String, error: Option<crate::error::Error>) {
+        self.status = Some(
+            error
+                .map(|err| format!("Error saving snapshot {name}: {err}"))
+                .unwrap_or_else(|| format!("Saved snapshot {name}")),
+        );
+    }
+
+    pub fn handle_snapshot_removed(&mut self, name: String, error: Option<crate::error::Error>) {
+        self.status = Some(
+            error
+                .map(|err| format!("Error removing snapshot {name}: {err}"))
+                .unwrap_or_else(|| format!("Removed snapshot {name}")),
+        );
+    }
+
+    fn handle_thread_search_key(&mut self, key: KeyEvent) {
+        match self.thread_search.handle_key(key) {
+            SearchAction::Selected(Some(name)) => self.show_thread(name),
+            SearchAction::Refresh => self.refresh_thread_search(),
             _ => {}
         }
     }
 
-    fn refresh_search(&mut self) {
-        let query = self.search.input.to_lowercase();
-        self.search.matches = self
-            .graph
+    /// Zooms into a thread.
+    pub fn show_thread(&mut self, name: String) {
+        self.butterfly = None;
+        self.zoom_path = vec![name];
+        self.cursor_path.clear();
+        self.scroll_y = 0;
+        self.refresh_matches();
+    }
+
+    fn refresh_thread_search(&mut self) {
+        let query = self.thread_search.input.to_lowercase();
+        self.thread_search.matches = self
+            .view()
             .root
             .children
             .iter()
@@ -143,25 +1075,95 @@ impl FlamegraphTab {
         self.zoom_path.extend(names);
         self.cursor_path.clear();
         self.scroll_y = 0;
+        self.refresh_matches();
     }
 
     fn zoom_out(&mut self) {
         if self.zoom_path.pop().is_some() {
             self.cursor_path.clear();
             self.scroll_y = 0;
+            self.refresh_matches();
+        } else if self.highlight.take().is_none() && self.range.take().is_some() {
+            self.clear_range_baseline();
+            self.cursor_path.clear();
+            self.scroll_y = 0;
+            self.rebuild_view();
+        }
+    }
+
+    fn mark_baseline(&mut self) {
+        self.baseline = Some(self.source().clone());
+        self.baseline_range = None;
+        self.rebuild_baseline_view();
+    }
+
+    fn clear_baseline(&mut self) {
+        self.baseline = None;
+        self.baseline_view = None;
+        self.baseline_range = None;
+        self.selection.baseline_pct = None;
+    }
+
+    /// A flamescope comparison ends with the range it was opened with.
+    fn clear_range_baseline(&mut self) {
+        if self.baseline_range.is_some() {
+            self.clear_baseline();
         }
     }
 
+    fn toggle_inverted(&mut self) {
+        self.inverted = !self.inverted;
+        self.rebuild_view();
+        self.rebuild_baseline_view();
+        // Thread names are shared by both trees, anything deeper is not.
+        self.zoom_path.truncate(1);
+        self.cursor_path.clear();
+        self.scroll_y = 0;
+        self.refresh_matches();
+    }
+
     fn reset(&mut self) {
         self.graph = FlameGraph::new();
+        self.nodes = 1;
         self.profiles_received = 0;
         self.samples_received = 0;
+        self.started_secs = None;
+        self.intervals.clear();
+        self.pruned_nodes = 0;
+        self.pruned_samples = 0;
         self.zoom_path.clear();
         self.cursor_path.clear();
         self.scroll_y = 0;
+        self.rebuild_view();
     }
 }
 
+pub(super) fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// `HH:MM:SS` of a unix time, in UTC.
+pub fn format_clock(secs: u64) -> String {
+    let day = secs % 86_400;
+    format!("{:02}:{:02}:{:02}", day / 3600, day % 3600 / 60, day % 60)
+}
+
+/// `YYYY-MM-DD HH:MM:SS` of a unix time, in UTC.
+pub fn format_datetime(secs: u64) -> String {
+    // Howard Hinnant's `civil_from_days`, with eras starting in March.
+    let days = secs / 86_400 + 719_468;
+    let (era, doe) = (days / 146_097, days % 146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = era * 400 + yoe + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02} {}", format_clock(secs))
+}
+
 fn collect_path_names(root: &FlameNode, index_path: &[usize]) -> Vec<String> {
     index_path
         .iter()
@@ -172,3 +1174,111 @@ fn collect_path_names(root: &FlameNode, index_path: &[usize]) -> Vec<String> {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn window_counts_only_its_samples() {
+        let mut tab = FlamegraphTab::default();
+        tab.set_window(Some(30));
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main"]), 5);
+        tab.merge(fg, 5);
+        assert_eq!((tab.profiles_received, tab.samples_received), (1, 5));
+        assert!(tab.started_secs.is_some());
+
+        assert!(tab.expire_at(unix_secs() + 30));
+        assert_eq!((tab.profiles_received, tab.samples_received), (0, 0));
+        assert_eq!(tab.started_secs, None);
+
+        // The custom window stays in the cycle, between the presets.
+        tab.cycle_window(true);
+        assert_eq!(tab.window_secs, Some(60));
+        tab.cycle_window(false);
+        assert_eq!(tab.window_secs, Some(30));
+        tab.cycle_window(false);
+        assert_eq!(tab.window_secs, Some(10));
+    }
+
+    #[test]
+    fn pruned_samples_expire_from_other() {
+        let mut tab = FlamegraphTab {
+            max_nodes: Some(5),
+            window_secs: Some(10),
+            ..FlamegraphTab::default()
+        };
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "hot"]), 20);
+        fg.add_stack(&stack(&["t1", "main", "cold", "x"]), 1);
+        tab.merge(fg, 21);
+        assert_eq!(tab.pruned_samples, 1);
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "cold"]), 2);
+        tab.merge(fg, 2);
+        assert_eq!(tab.nodes, tab.graph.root.node_count());
+
+        tab.expire_at(unix_secs() + 60);
+        assert_eq!(tab.graph.root.total_value, 0);
+        assert!(tab.graph.root.children.is_empty());
+        assert_eq!(tab.nodes, 1);
+    }
+
+    #[test]
+    fn functions_ignore_inversion() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "work", "leaf"]), 10);
+        fg.add_stack(&stack(&["t1", "main"]), 2);
+        let mut tab = FlamegraphTab::default();
+        tab.merge(fg, 12);
+
+        let self_of = |tab: &FlamegraphTab, name: &str| {
+            let functions = tab.functions_graph().functions();
+            functions
+                .iter()
+                .find(|f| f.name == name)
+                .unwrap()
+                .self_value
+        };
+        assert_eq!((self_of(&tab, "leaf"), self_of(&tab, "main")), (10, 2));
+        tab.toggle_inverted();
+        // The inverted view ends every stack at its outermost caller.
+        let inverted = tab.view().functions();
+        assert!(
+            inverted
+                .iter()
+                .any(|f| f.name == "leaf" && f.self_value == 0)
+        );
+        assert_eq!((self_of(&tab, "leaf"), self_of(&tab, "main")), (10, 2));
+    }
+
+    #[test]
+    fn butterfly_scroll_stays_within_levels() {
+        let mut fg = FlameGraph::new();
+        fg.add_stack(&stack(&["t1", "main", "a", "b", "work", "leaf"]), 3);
+        let mut tab = FlamegraphTab::default();
+        tab.merge(fg, 3);
+        tab.open_butterfly("work");
+
+        // Callers: b, a, main, t1; callees: leaf.
+        for _ in 0..10 {
+            tab.handle_key(KeyCode::Char('k').into(), &[]);
+            tab.handle_key(KeyCode::Char('J').into(), &[]);
+        }
+        assert_eq!(tab.butterfly_scroll, [3, 0]);
+        tab.handle_key(KeyCode::Char('j').into(), &[]);
+        assert_eq!(tab.butterfly_scroll, [2, 0]);
+    }
+
+    #[test]
+    fn datetimes_carry_the_date() {
+        assert_eq!(format_datetime(0), "1970-01-01 00:00:00");
+        assert_eq!(format_datetime(1_700_000_000), "2023-11-14 22:13:20");
+        assert_eq!(format_datetime(951_825_600), "2000-02-29 12:00:00");
+    }
+}
@@ -1,85 +1,555 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Rect;
 
+use super::flamegraph::TimeRange;
 use super::{SearchAction, SearchOverlay};
+use crate::flamegraph::{FlameGraph, Stack, Timestamps};
+use crate::markers::Marker;
+use crate::tui::flamescope_layout::FlamescopeLayout;
 
-const SUBSECOND_ROWS: usize = 10;
+/// Rows per column cycled with `+`/`-`.
+const ROW_PRESETS: &[usize] = &[10, 20, 50, 100];
+/// Seconds per column cycled with `]`/`[`.
+const COLUMN_SECS_PRESETS: &[u64] = &[1, 2, 5, 10];
 const NS_PER_SEC: u64 = 1_000_000_000;
-const NS_PER_ROW: u64 = NS_PER_SEC / SUBSECOND_ROWS as u64;
+const NS_PER_MS: u64 = 1_000_000;
+pub const DEFAULT_RETENTION_SECS: u64 = 30 * 60;
+/// How far behind or ahead of the newest sample a sample may fall when
+/// every column is kept, since the grid has a column for every second in
+/// between.
+const MAX_JUMP_SECS: u64 = 60 * 60;
+/// Samples in a row too far ahead of the newest one after which the
+/// timeline restarts at them, taking the agent's clock to have jumped.
+const RESTART_AFTER: usize = 32;
+/// Most markers kept; the oldest go first.
+const MAX_MARKERS: usize = 1024;
 
-pub struct FlamescopeTab {
+/// Share of the non-empty cells below the top color of the percentile
+/// scale.
+const CLIP_PERCENTILE: f64 = 0.99;
+
+/// How cell counts map to heatmap colors, cycled with `c`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum HeatmapScale {
+    #[default]
+    Sqrt,
+    Log,
+    /// Linear up to the 99th percentile of the non-empty cells, so a few
+    /// hot cells don't wash out the rest.
+    Percentile,
+    Linear,
+}
+
+impl HeatmapScale {
+    const ALL: [HeatmapScale; 4] = [
+        HeatmapScale::Sqrt,
+        HeatmapScale::Log,
+        HeatmapScale::Percentile,
+        HeatmapScale::Linear,
+    ];
+
+    fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HeatmapScale::Sqrt => "sqrt",
+            HeatmapScale::Log => "log",
+            HeatmapScale::Percentile => "p99",
+            HeatmapScale::Linear => "linear",
+        }
+    }
+
+    /// Position of `value` between 0 and `max` on this scale, from 0 to 1.
+    pub fn fraction(self, value: u64, max: u64) -> f64 {
+        if max == 0 {
+            return 0.0;
+        }
+        let (value, max) = (value as f64, max as f64);
+        let t = match self {
+            HeatmapScale::Sqrt => value.sqrt() / max.sqrt(),
+            HeatmapScale::Log => value.ln_1p() / max.ln_1p(),
+            HeatmapScale::Percentile | HeatmapScale::Linear => value / max,
+        };
+        t.clamp(0.0, 1.0)
+    }
+
+    /// Inverse of [`HeatmapScale::fraction`], rounded to a whole count.
+    pub fn value_at(self, t: f64, max: u64) -> u64 {
+        let max = max as f64;
+        let value = match self {
+            HeatmapScale::Sqrt => t * t * max,
+            HeatmapScale::Log => (t * max.ln_1p()).exp_m1(),
+            HeatmapScale::Percentile | HeatmapScale::Linear => t * max,
+        };
+        value.round() as u64
+    }
+}
+
+/// Heatmap data of one resource. Every resource keeps its own time base,
+/// so agents whose clocks disagree don't smear each other's samples.
+struct Timeline {
+    rows: usize,
+    col_ns: u64,
+    /// First sample ever recorded, rounded down to the second; cell spans
+    /// are relative to it.
+    origin_ns: Option<u64>,
+    /// Start of the first column, a multiple of the column length.
     epoch_ns: Option<u64>,
-    columns: Vec<[u64; SUBSECOND_ROWS]>,
-    threads: HashMap<String, Vec<[u64; SUBSECOND_ROWS]>>,
+    newest_ns: u64,
+    columns: Vec<Vec<u64>>,
+    threads: HashMap<String, Vec<Vec<u64>>>,
     thread_names: Vec<String>,
+    /// Every timestamped sample with its weight and stack, in arrival
+    /// order.
+    samples: Vec<(u64, u64, Stack)>,
+    /// Some samples had no timestamps and were spread over their profile's
+    /// interval.
+    approximated: bool,
+    /// Bumped whenever a cell count changes.
+    version: u64,
+    /// Samples dropped in a row for being too far ahead of the newest one.
+    ahead: usize,
+}
+
+/// Peak and hottest color of the shown cells, kept until the key they
+/// were computed for changes.
+struct ColorStats {
+    /// Resource, timeline version, filter, global normalization and scale.
+    key: (Option<String>, u64, Option<String>, bool, HeatmapScale),
+    peak: u64,
+    color_max: u64,
+}
+
+impl Timeline {
+    fn new(rows: usize, col_ns: u64) -> Self {
+        Self {
+            rows,
+            col_ns,
+            origin_ns: None,
+            epoch_ns: None,
+            newest_ns: 0,
+            columns: Vec::new(),
+            threads: HashMap::new(),
+            thread_names: Vec::new(),
+            samples: Vec::new(),
+            approximated: false,
+            version: 0,
+            ahead: 0,
+        }
+    }
+
+    /// Whether `ts` is more than `limit_ns` behind the newest sample, and so
+    /// would be expired right away or rebase the grid too far back.
+    fn too_old(&self, ts: u64, limit_ns: u64) -> bool {
+        self.epoch_ns.is_some() && ts.saturating_add(limit_ns) < self.newest_ns
+    }
+
+    /// Whether `ts` is more than `limit_ns` ahead of the newest sample, so
+    /// that every column recorded so far would be expired once it is. Such
+    /// samples are dropped until [`RESTART_AFTER`] came in a row.
+    fn too_new(&mut self, ts: u64, limit_ns: u64) -> bool {
+        if self.epoch_ns.is_none() || ts <= self.newest_ns.saturating_add(limit_ns) {
+            self.ahead = 0;
+            return false;
+        }
+        self.ahead += 1;
+        true
+    }
+
+    /// Forgets every sample, returning the number of columns dropped.
+    fn restart(&mut self) -> usize {
+        let dropped = self.columns.len();
+        *self = Self {
+            approximated: self.approximated,
+            version: self.version + 1,
+            ..Self::new(self.rows, self.col_ns)
+        };
+        dropped
+    }
+
+    /// Records a sample, returning the number of columns inserted in front
+    /// when it predates every column.
+    fn record(&mut self, thread: &str, ts: u64, weight: u64, stack: &Stack) -> usize {
+        if !self.threads.contains_key(thread) {
+            let pos = self
+                .thread_names
+                .binary_search_by(|name| name.as_str().cmp(thread))
+                .unwrap_or_else(|e| e);
+            self.thread_names.insert(pos, thread.to_string());
+        }
+        let origin = ts - ts % NS_PER_SEC;
+        self.origin_ns = Some(self.origin_ns.map_or(origin, |o| o.min(origin)));
+        self.newest_ns = self.newest_ns.max(ts);
+        let inserted = self.rebase(ts);
+        self.bucket(ts, weight, thread);
+        self.samples.push((ts, weight, Stack::clone(stack)));
+        inserted
+    }
+
+    /// Moves the epoch back by whole columns so that `ts` falls in the
+    /// first one, shifting the existing columns right.
+    fn rebase(&mut self, ts: u64) -> usize {
+        let aligned = ts - ts % self.col_ns;
+        let epoch = *self.epoch_ns.get_or_insert(aligned);
+        if aligned >= epoch {
+            return 0;
+        }
+        let inserted = ((epoch - aligned) / self.col_ns) as usize;
+        self.epoch_ns = Some(aligned);
+        let empty = vec![vec![0; self.rows]; inserted];
+        for cols in std::iter::once(&mut self.columns).chain(self.threads.values_mut()) {
+            cols.splice(0..0, empty.iter().cloned());
+        }
+        inserted
+    }
+
+    fn bucket(&mut self, ts: u64, weight: u64, thread: &str) {
+        let (col, row) = self.cell_of(ts);
+        let rows = self.rows;
+        let thread_cols = match self.threads.get_mut(thread) {
+            Some(cols) => cols,
+            None => self.threads.entry(thread.to_string()).or_default(),
+        };
+        for cols in [&mut self.columns, thread_cols] {
+            if cols.len() <= col {
+                cols.resize(col + 1, vec![0; rows]);
+            }
+            cols[col][row] += weight;
+        }
+        self.version += 1;
+    }
+
+    /// Drops the columns that ended more than `retention_secs` before
+    /// `newest_ns`, along with their samples and the threads left without
+    /// any. Returns the number of columns dropped.
+    fn expire(&mut self, newest_ns: u64, retention_secs: u64) -> usize {
+        let Some(epoch) = self.epoch_ns else {
+            return 0;
+        };
+        let cutoff = newest_ns.saturating_sub(retention_secs * NS_PER_SEC);
+        let drop = ((cutoff.saturating_sub(epoch) / self.col_ns) as usize).min(self.columns.len());
+        if drop == 0 {
+            return 0;
+        }
+
+        let epoch = epoch + drop as u64 * self.col_ns;
+        self.epoch_ns = Some(epoch);
+        self.version += 1;
+        self.samples.retain(|(ts, _, _)| *ts >= epoch);
+        self.columns.drain(..drop);
+        for cols in self.threads.values_mut() {
+            cols.drain(..drop.min(cols.len()));
+        }
+        self.threads
+            .retain(|_, cols| cols.iter().flatten().any(|&count| count > 0));
+        self.thread_names
+            .retain(|name| self.threads.contains_key(name));
+        drop
+    }
+
+    /// Re-buckets every sample into a grid of another resolution.
+    fn rebucket(&mut self, rows: usize, col_ns: u64) {
+        self.rows = rows;
+        self.col_ns = col_ns;
+        self.version += 1;
+        self.epoch_ns = self.samples.iter().map(|(ts, _, _)| ts - ts % col_ns).min();
+        self.columns.clear();
+        self.threads.clear();
+        let samples = std::mem::take(&mut self.samples);
+        for (ts, weight, stack) in &samples {
+            self.bucket(*ts, *weight, &stack[0].name);
+        }
+        self.samples = samples;
+    }
+
+    fn row_ns(&self) -> u64 {
+        self.col_ns / self.rows as u64
+    }
+
+    /// Column and row of a timestamp.
+    fn cell_of(&self, ts: u64) -> (usize, usize) {
+        let offset = ts.saturating_sub(self.epoch_ns.unwrap_or(ts));
+        let col = (offset / self.col_ns) as usize;
+        let row = ((offset % self.col_ns) / self.row_ns()) as usize;
+        (col, row.min(self.rows - 1))
+    }
+
+    /// Timestamp at which a cell starts.
+    fn cell_ts(&self, (col, row): (usize, usize)) -> u64 {
+        self.epoch_ns.unwrap_or(0) + col as u64 * self.col_ns + row as u64 * self.row_ns()
+    }
+}
+
+/// A range marked with `b`; ranges opened afterwards are diffed against it.
+pub struct Baseline {
+    pub range: TimeRange,
+    resource: Option<String>,
+    start_ns: u64,
+    end_ns: u64,
+}
+
+pub struct FlamescopeTab {
+    /// Rows each column is split into.
+    pub rows: usize,
+    pub secs_per_col: u64,
+    /// How far behind the newest sample columns are kept; `None` keeps
+    /// everything.
+    pub retention_secs: Option<u64>,
+    timelines: BTreeMap<String, Timeline>,
+    /// Resource whose timeline is shown.
+    pub resource: Option<String>,
     pub filter: Option<String>,
     pub search: SearchOverlay,
     pub auto_scroll: bool,
     pub scroll_x: usize,
+    pub scroll_y: usize,
     pub cursor_col: usize,
     pub cursor_row: usize,
+    /// Cell where a mouse drag started; the selection spans from it to the
+    /// cursor.
+    pub anchor: Option<(usize, usize)>,
+    pub baseline: Option<Baseline>,
+    pub scale: HeatmapScale,
+    /// Whether a filtered thread is colored against the peak of all threads
+    /// rather than its own.
+    pub global_scale: bool,
+    /// Markers of every resource, by time.
+    pub markers: Vec<Marker>,
+    /// Label prompt of a marker dropped with `a`.
+    pub marker_name: SearchOverlay,
+    /// Where the heatmap was last drawn, for mouse hit testing.
+    pub grid_area: Rect,
+    color_stats: Option<ColorStats>,
 }
 
 impl Default for FlamescopeTab {
     fn default() -> Self {
         Self {
-            epoch_ns: None,
-            columns: Vec::new(),
-            threads: HashMap::new(),
-            thread_names: Vec::new(),
+            rows: ROW_PRESETS[0],
+            secs_per_col: COLUMN_SECS_PRESETS[0],
+            retention_secs: Some(DEFAULT_RETENTION_SECS),
+            timelines: BTreeMap::new(),
+            resource: None,
             filter: None,
             search: SearchOverlay::default(),
             auto_scroll: true,
             scroll_x: 0,
+            scroll_y: 0,
             cursor_col: 0,
             cursor_row: 0,
+            anchor: None,
+            baseline: None,
+            scale: HeatmapScale::default(),
+            global_scale: false,
+            markers: Vec::new(),
+            marker_name: SearchOverlay::default(),
+            grid_area: Rect::default(),
+            color_stats: None,
         }
     }
 }
 
 impl FlamescopeTab {
-    pub const ROWS: usize = SUBSECOND_ROWS;
-
-    pub fn record_timestamps(&mut self, entries: &HashMap<String, Vec<u64>>) {
-        for (thread, timestamps) in entries {
-            if !self.threads.contains_key(thread) {
-                let pos = self
-                    .thread_names
-                    .binary_search(thread)
-                    .unwrap_or_else(|e| e);
-                self.thread_names.insert(pos, thread.clone());
-            }
-
-            for &ts in timestamps {
-                let epoch = *self.epoch_ns.get_or_insert(ts);
-                let offset = ts.saturating_sub(epoch);
-                let col = (offset / NS_PER_SEC) as usize;
-                let row =
-                    ((offset % NS_PER_SEC) / NS_PER_ROW).min(SUBSECOND_ROWS as u64 - 1) as usize;
-
-                while self.columns.len() <= col {
-                    self.columns.push([0; SUBSECOND_ROWS]);
+    pub fn record_timestamps(&mut self, entries: &Timestamps) {
+        // Columns inserted in front of and dropped from the shown timeline.
+        let mut shown = (0, 0);
+        for (resource, recorded) in entries {
+            let (rows, col_ns) = (self.rows, self.col_ns());
+            let timeline = self
+                .timelines
+                .entry(resource.clone())
+                .or_insert_with(|| Timeline::new(rows, col_ns));
+            timeline.approximated |= recorded.approximated;
+            // A stray timestamp, e.g. from an agent with an unset clock,
+            // must not allocate a column for every second up to it.
+            let limit_ns = self.retention_secs.unwrap_or(MAX_JUMP_SECS) * NS_PER_SEC;
+            let (mut inserted, mut dropped) = (0, 0);
+            for (thread, timestamps) in &recorded.threads {
+                for (ts, weight, stack) in timestamps {
+                    if timeline.too_old(*ts, limit_ns) {
+                        continue;
+                    }
+                    if timeline.too_new(*ts, limit_ns) {
+                        if timeline.ahead < RESTART_AFTER {
+                            continue;
+                        }
+                        dropped += timeline.restart() + inserted;
+                        inserted = 0;
+                    }
+                    inserted += timeline.record(thread, *ts, *weight, stack);
                 }
-                self.columns[col][row] += 1;
+            }
+            if self.resource.get_or_insert_with(|| resource.clone()) == resource {
+                shown = (inserted, dropped);
+            }
+        }
+        let (inserted, dropped) = shown;
+        let expired = self.expire();
+        self.shift_columns(inserted, dropped + expired);
+    }
 
-                let thread_cols = self.threads.entry(thread.clone()).or_default();
-                while thread_cols.len() <= col {
-                    thread_cols.push([0; SUBSECOND_ROWS]);
-                }
-                thread_cols[col][row] += 1;
+    /// Expires every timeline against the newest sample of any resource,
+    /// so that resources that stopped reporting age out too, and forgets
+    /// the timelines left empty. Returns the number of columns dropped from
+    /// the shown timeline.
+    fn expire(&mut self) -> usize {
+        let Some(retention_secs) = self.retention_secs else {
+            return 0;
+        };
+        let Some(newest_ns) = self.timelines.values().map(|t| t.newest_ns).max() else {
+            return 0;
+        };
+        let mut dropped = 0;
+        for (resource, timeline) in &mut self.timelines {
+            let expired = timeline.expire(newest_ns, retention_secs);
+            if self.resource.as_ref() == Some(resource) {
+                dropped = expired;
+            }
+        }
+        self.timelines
+            .retain(|_, timeline| !timeline.columns.is_empty());
+        if let Some(resource) = &self.resource
+            && !self.timelines.contains_key(resource)
+        {
+            self.resource = self.timelines.keys().next().cloned();
+            self.filter = None;
+            self.anchor = None;
+            (self.cursor_col, self.scroll_x) = (0, 0);
+            self.auto_scroll = true;
+            return 0;
+        }
+        dropped
+    }
+
+    /// Keeps the cursor, scroll position and selection on the same columns
+    /// after columns were inserted in front or dropped from there.
+    fn shift_columns(&mut self, inserted: usize, dropped: usize) {
+        let shift = |col: usize| (col + inserted).saturating_sub(dropped);
+        self.cursor_col = shift(self.cursor_col);
+        self.scroll_x = shift(self.scroll_x);
+        self.anchor = self.anchor.map(|(col, row)| {
+            if col + inserted >= dropped {
+                (shift(col), row)
+            } else {
+                (0, 0)
             }
+        });
+        if self
+            .filter
+            .as_ref()
+            .is_some_and(|name| !self.thread_names().contains(name))
+        {
+            self.filter = None;
         }
     }
 
+    fn timeline(&self) -> Option<&Timeline> {
+        self.timelines.get(self.resource.as_ref()?)
+    }
+
+    pub fn thread_names(&self) -> &[String] {
+        self.timeline().map_or(&[], |t| t.thread_names.as_slice())
+    }
+
+    /// Position of the shown resource among all of them, and their count.
+    pub fn resource_position(&self) -> (usize, usize) {
+        let pos = self
+            .resource
+            .as_ref()
+            .and_then(|r| self.timelines.keys().position(|name| name == r))
+            .unwrap_or(0);
+        (pos, self.timelines.len())
+    }
+
+    fn cycle_resource(&mut self) {
+        let (pos, count) = self.resource_position();
+        if count < 2 {
+            return;
+        }
+        self.resource = self.timelines.keys().nth((pos + 1) % count).cloned();
+        self.filter = None;
+        self.anchor = None;
+        (self.cursor_col, self.scroll_x) = (0, 0);
+        self.auto_scroll = true;
+    }
+
+    /// Changes the grid resolution, re-bucketing every recorded sample and
+    /// keeping the cursor and selection on the same points in time.
+    fn set_resolution(&mut self, rows: usize, secs_per_col: u64) {
+        let at = |fs: &Self, cell| fs.timeline().map(|t| t.cell_ts(cell));
+        let cursor = at(self, (self.cursor_col, self.cursor_row));
+        let anchor = self.anchor.and_then(|cell| at(self, cell));
+        self.rows = rows;
+        self.secs_per_col = secs_per_col;
+        let col_ns = self.col_ns();
+        for timeline in self.timelines.values_mut() {
+            timeline.rebucket(rows, col_ns);
+        }
+        let cell_of = |fs: &Self, ts| fs.timeline().map(|t| t.cell_of(ts));
+        if let Some(cell) = cursor.and_then(|ts| cell_of(self, ts)) {
+            (self.cursor_col, self.cursor_row) = cell;
+        }
+        self.anchor = anchor.and_then(|ts| cell_of(self, ts));
+    }
+
+    fn cycle_rows(&mut self, finer: bool) {
+        let rows = cycle(ROW_PRESETS, self.rows, finer);
+        self.set_resolution(rows, self.secs_per_col);
+    }
+
+    fn cycle_column_secs(&mut self, wider: bool) {
+        let secs = cycle(COLUMN_SECS_PRESETS, self.secs_per_col, wider);
+        self.set_resolution(self.rows, secs);
+    }
+
+    fn col_ns(&self) -> u64 {
+        self.secs_per_col * NS_PER_SEC
+    }
+
+    pub fn row_ns(&self) -> u64 {
+        self.col_ns() / self.rows as u64
+    }
+
+    /// Start and end of a cell, in nanoseconds since the first sample.
+    pub fn cell_span(&self, cell: (usize, usize)) -> (u64, u64) {
+        let Some(timeline) = self.timeline() else {
+            return (0, self.row_ns());
+        };
+        let start = timeline
+            .cell_ts(cell)
+            .saturating_sub(timeline.origin_ns.unwrap_or(0));
+        (start, start + self.row_ns())
+    }
+
+    /// Wall-clock start of a column, in unix seconds.
+    pub fn column_unix_secs(&self, col: usize) -> Option<u64> {
+        self.timeline()
+            .filter(|t| t.epoch_ns.is_some())
+            .map(|t| t.cell_ts((col, 0)) / NS_PER_SEC)
+    }
+
+    /// Whether the shown timeline places some samples by their profile's
+    /// interval rather than by their own timestamps.
+    pub fn is_approximated(&self) -> bool {
+        self.timeline().is_some_and(|t| t.approximated)
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.columns.is_empty()
+        self.timeline().is_none_or(|t| t.columns.is_empty())
     }
 
-    pub fn visible_columns(&self) -> &[[u64; SUBSECOND_ROWS]] {
+    pub fn visible_columns(&self) -> &[Vec<u64>] {
+        let Some(timeline) = self.timeline() else {
+            return &[];
+        };
         match &self.filter {
-            Some(name) => self.threads.get(name).map_or(&[], |v| v.as_slice()),
-            None => &self.columns,
+            Some(name) => timeline.threads.get(name).map_or(&[], |v| v.as_slice()),
+            None => &timeline.columns,
         }
     }
 
@@ -89,28 +559,232 @@ impl FlamescopeTab {
             .map_or(0, |col| col[self.cursor_row])
     }
 
-    pub fn selected_time(&self) -> (usize, usize, usize) {
-        let ms_start = (self.cursor_row * 1000) / SUBSECOND_ROWS;
-        let ms_end = ((self.cursor_row + 1) * 1000) / SUBSECOND_ROWS;
-        (self.cursor_col, ms_start, ms_end)
+    pub fn selected_time(&self) -> (u64, u64) {
+        self.cell_span((self.cursor_col, self.cursor_row))
     }
 
-    pub fn visible_peak(&self) -> u64 {
+    /// Time span covered by the selection.
+    pub fn selection_span(&self) -> Option<(u64, u64)> {
+        let (start, end) = self.selection()?;
+        Some((self.cell_span(start).0, self.cell_span(end).1))
+    }
+
+    /// First and last `(column, row)` cell of the selection, in time order.
+    pub fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.anchor?;
+        let cursor = (self.cursor_col, self.cursor_row);
+        Some((anchor.min(cursor), anchor.max(cursor)))
+    }
+
+    pub fn selection_value(&self) -> u64 {
+        let Some((start, end)) = self.selection() else {
+            return 0;
+        };
         self.visible_columns()
             .iter()
-            .flat_map(|col| col.iter())
+            .enumerate()
+            .take(end.0 + 1)
+            .skip(start.0)
+            .flat_map(|(col, rows)| {
+                rows.iter()
+                    .enumerate()
+                    .filter(move |&(row, _)| (start..=end).contains(&(col, row)))
+                    .map(|(_, &value)| value)
+            })
+            .sum()
+    }
+
+    /// Flamegraph of exactly the samples in the selection, or in the cursor
+    /// cell without one, restricted to the filtered thread.
+    pub fn range_graph(&self) -> TimeRange {
+        let cursor = (self.cursor_col, self.cursor_row);
+        let (start, end) = self.selection().unwrap_or((cursor, cursor));
+        let mut graph = FlameGraph::new();
+        let mut samples = 0;
+        if let Some(timeline) = self.timeline() {
+            for (ts, weight, stack) in &timeline.samples {
+                if self.filter.as_ref().is_some_and(|f| stack[0].name != *f) {
+                    continue;
+                }
+                if (start..=end).contains(&timeline.cell_of(*ts)) {
+                    graph.add_stack(stack, *weight as i64);
+                    samples += weight;
+                }
+            }
+        }
+        graph.root.sort_recursive();
+        let mut label = format_span(self.cell_span(start).0, self.cell_span(end).1);
+        if let Some(ref thread) = self.filter {
+            label.push_str(&format!(" of {thread}"));
+        }
+        if let Some(ref resource) = self.resource
+            && self.timelines.len() > 1
+        {
+            label.push_str(&format!(" on {resource}"));
+        }
+        TimeRange {
+            label,
+            samples,
+            graph,
+        }
+    }
+
+    /// Marks the selection, or the cursor cell without one, as the
+    /// baseline.
+    fn mark_baseline(&mut self) {
+        let Some(timeline) = self.timeline() else {
+            return;
+        };
+        let cursor = (self.cursor_col, self.cursor_row);
+        let (start, end) = self.selection().unwrap_or((cursor, cursor));
+        let (start_ns, end_ns) = (
+            timeline.cell_ts(start),
+            timeline.cell_ts(end) + self.row_ns(),
+        );
+        self.baseline = Some(Baseline {
+            range: self.range_graph(),
+            resource: self.resource.clone(),
+            start_ns,
+            end_ns,
+        });
+        self.anchor = None;
+    }
+
+    pub fn in_baseline(&self, cell: (usize, usize)) -> bool {
+        let (Some(baseline), Some(timeline)) = (&self.baseline, self.timeline()) else {
+            return false;
+        };
+        baseline.resource == self.resource
+            && (baseline.start_ns..baseline.end_ns).contains(&timeline.cell_ts(cell))
+    }
+
+    /// Adds a marker, dropping those that fell out of the retention window
+    /// before it and the oldest beyond [`MAX_MARKERS`].
+    pub fn add_marker(&mut self, marker: Marker) {
+        if let Some(secs) = self.retention_secs {
+            let cutoff = marker.unix_nano.saturating_sub(secs * NS_PER_SEC);
+            self.markers.retain(|m| m.unix_nano >= cutoff);
+        }
+        let pos = self
+            .markers
+            .partition_point(|m| m.unix_nano <= marker.unix_nano);
+        self.markers.insert(pos, marker);
+        let excess = self.markers.len().saturating_sub(MAX_MARKERS);
+        self.markers.drain(..excess);
+    }
+
+    /// Cell of a marker on the shown timeline, if it falls within the
+    /// recorded columns. Markers use the local clock, so they only line up
+    /// with agents whose clocks agree with it.
+    pub fn marker_cell(&self, marker: &Marker) -> Option<(usize, usize)> {
+        let timeline = self.timeline()?;
+        if marker.unix_nano < timeline.epoch_ns? {
+            return None;
+        }
+        let cell = timeline.cell_of(marker.unix_nano);
+        (cell.0 < self.visible_columns().len()).then_some(cell)
+    }
+
+    /// Markers falling in a column.
+    pub fn column_markers(&self, col: usize) -> impl Iterator<Item = &Marker> {
+        self.markers
+            .iter()
+            .filter(move |m| self.marker_cell(m).is_some_and(|cell| cell.0 == col))
+    }
+
+    /// Moves the cursor to the next or previous marker, so that markers can
+    /// bound a selection.
+    fn jump_to_marker(&mut self, forward: bool) {
+        let cursor = (self.cursor_col, self.cursor_row);
+        let mut cells = self.markers.iter().filter_map(|m| self.marker_cell(m));
+        let target = if forward {
+            cells.find(|&cell| cell > cursor)
+        } else {
+            cells.rfind(|&cell| cell < cursor)
+        };
+        if let Some(cell) = target {
+            self.auto_scroll = false;
+            (self.cursor_col, self.cursor_row) = cell;
+        }
+    }
+
+    pub fn visible_peak(&mut self) -> u64 {
+        self.color_stats().peak
+    }
+
+    /// Count shown in the hottest color: the peak of the shown thread, or
+    /// of all threads when normalizing globally, clipped to a percentile of
+    /// the non-empty cells on the percentile scale.
+    pub fn color_max(&mut self) -> u64 {
+        self.color_stats().color_max
+    }
+
+    /// Recomputes the peak and hottest color only once samples, resolution,
+    /// resource, filter or scale changed.
+    fn color_stats(&mut self) -> &ColorStats {
+        let key = (
+            self.resource.clone(),
+            self.timeline().map_or(0, |t| t.version),
+            self.filter.clone(),
+            self.global_scale,
+            self.scale,
+        );
+        if self
+            .color_stats
+            .as_ref()
+            .is_none_or(|stats| stats.key != key)
+        {
+            let peak = self
+                .visible_columns()
+                .iter()
+                .flatten()
+                .copied()
+                .max()
+                .unwrap_or(0);
+            let color_max = self.compute_color_max();
+            self.color_stats = Some(ColorStats {
+                key,
+                peak,
+                color_max,
+            });
+        }
+        self.color_stats.as_ref().expect("computed above")
+    }
+
+    fn compute_color_max(&self) -> u64 {
+        let columns = match self.timeline() {
+            Some(timeline) if self.global_scale => &timeline.columns,
+            _ => self.visible_columns(),
+        };
+        let mut counts: Vec<u64> = columns
+            .iter()
+            .flatten()
             .copied()
-            .max()
-            .unwrap_or(0)
+            .filter(|&count| count > 0)
+            .collect();
+        if counts.is_empty() {
+            return 0;
+        }
+        if self.scale != HeatmapScale::Percentile {
+            return counts.into_iter().max().unwrap_or(0);
+        }
+        let i = ((counts.len() - 1) as f64 * CLIP_PERCENTILE).round() as usize;
+        *counts.select_nth_unstable(i).1
     }
 
-    pub fn total_seconds(&self) -> usize {
-        self.visible_columns().len()
+    pub fn total_seconds(&self) -> u64 {
+        self.visible_columns().len() as u64 * self.secs_per_col
     }
 
-    pub(crate) fn handle_key(&mut self, key: KeyEvent) {
+    /// Returns the flamegraph of the selected range when one is opened.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> Option<TimeRange> {
         if self.search.active {
-            return self.handle_search_key(key);
+            self.handle_search_key(key);
+            return None;
+        }
+        if self.marker_name.active {
+            self.handle_marker_name_key(key);
+            return None;
         }
         match key.code {
             KeyCode::Right | KeyCode::Char('l') => {
@@ -125,7 +799,7 @@ impl FlamescopeTab {
                 self.cursor_col = self.cursor_col.saturating_sub(1);
             }
             KeyCode::Down | KeyCode::Char('j') => {
-                if self.cursor_row + 1 < SUBSECOND_ROWS {
+                if self.cursor_row + 1 < self.rows {
                     self.cursor_row += 1;
                 }
             }
@@ -138,36 +812,459 @@ impl FlamescopeTab {
             }
             KeyCode::Esc => {
                 self.filter = None;
+                self.anchor = None;
                 self.auto_scroll = true;
             }
             KeyCode::Char('G') | KeyCode::End => {
                 self.auto_scroll = true;
             }
-            KeyCode::Char('r') => *self = Self::default(),
+            KeyCode::Char(' ') => {
+                self.auto_scroll = false;
+                self.anchor = match self.anchor {
+                    Some(_) => None,
+                    None => Some((self.cursor_col, self.cursor_row)),
+                };
+            }
+            KeyCode::Enter if !self.visible_columns().is_empty() => {
+                return Some(self.range_graph());
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => self.cycle_rows(true),
+            KeyCode::Char('-') => self.cycle_rows(false),
+            KeyCode::Char(']') => self.cycle_column_secs(true),
+            KeyCode::Char('[') => self.cycle_column_secs(false),
+            KeyCode::Char('R') => self.cycle_resource(),
+            KeyCode::Char('b') if !self.visible_columns().is_empty() => self.mark_baseline(),
+            KeyCode::Char('B') => self.baseline = None,
+            KeyCode::Char('c') => self.scale = self.scale.next(),
+            KeyCode::Char('g') => self.global_scale = !self.global_scale,
+            KeyCode::Char('a') => self.marker_name.open(),
+            KeyCode::Char('n') => self.jump_to_marker(true),
+            KeyCode::Char('N') => self.jump_to_marker(false),
+            KeyCode::Char('r') => {
+                *self = Self {
+                    rows: self.rows,
+                    secs_per_col: self.secs_per_col,
+                    retention_secs: self.retention_secs,
+                    scale: self.scale,
+                    global_scale: self.global_scale,
+                    markers: std::mem::take(&mut self.markers),
+                    ..Self::default()
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    pub(crate) fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let Some((col_off, row_off)) = FlamescopeLayout::new(self.grid_area, self.rows)
+            .and_then(|lay| lay.cell_at(mouse.column, mouse.row))
+        else {
+            return;
+        };
+        let cell = (self.scroll_x + col_off, self.scroll_y + row_off);
+        if cell.0 >= self.visible_columns().len() {
+            return;
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.auto_scroll = false;
+                self.anchor = Some(cell);
+                (self.cursor_col, self.cursor_row) = cell;
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.anchor.is_some() => {
+                (self.cursor_col, self.cursor_row) = cell;
+            }
+            // A click without a drag only moves the cursor.
+            MouseEventKind::Up(MouseButton::Left) if self.anchor == Some(cell) => {
+                self.anchor = None;
+            }
             _ => {}
         }
     }
 
     fn handle_search_key(&mut self, key: KeyEvent) {
         match self.search.handle_key(key) {
-            SearchAction::Selected(Some(name)) => {
-                self.filter = Some(name);
-                self.cursor_col = 0;
-                self.scroll_x = 0;
-                self.auto_scroll = true;
-            }
+            SearchAction::Selected(Some(name)) => self.show_thread(name),
             SearchAction::Refresh => self.refresh_search(),
             _ => {}
         }
     }
 
+    fn handle_marker_name_key(&mut self, key: KeyEvent) {
+        if key.code != KeyCode::Enter {
+            self.marker_name.handle_key(key);
+            return;
+        }
+        let label = self.marker_name.input.trim().to_string();
+        self.marker_name.close();
+        if !label.is_empty() {
+            self.add_marker(Marker::now(label));
+        }
+    }
+
+    /// Shows a single thread, following its latest samples.
+    pub fn show_thread(&mut self, name: String) {
+        self.filter = Some(name);
+        self.anchor = None;
+        self.cursor_col = 0;
+        self.scroll_x = 0;
+        self.auto_scroll = true;
+    }
+
     fn refresh_search(&mut self) {
         let query = self.search.input.to_lowercase();
         self.search.matches = self
-            .thread_names
+            .thread_names()
             .iter()
             .filter(|name| query.is_empty() || name.to_lowercase().contains(&query))
             .cloned()
             .collect();
     }
 }
+
+/// Next or previous preset after `current`, staying at the ends.
+fn cycle<T: Copy + PartialEq>(presets: &[T], current: T, forward: bool) -> T {
+    let i = presets.iter().position(|&p| p == current).unwrap_or(0);
+    let i = if forward {
+        (i + 1).min(presets.len() - 1)
+    } else {
+        i.saturating_sub(1)
+    };
+    presets[i]
+}
+
+/// `3s + 200–300ms`, or `3s + 900ms – 5s + 100ms` across seconds, for an
+/// end-exclusive span of nanoseconds since the first sample.
+pub fn format_span(start_ns: u64, end_ns: u64) -> String {
+    let (start_s, start_ms) = (start_ns / NS_PER_SEC, start_ns % NS_PER_SEC / NS_PER_MS);
+    let end_s = end_ns.saturating_sub(1) / NS_PER_SEC;
+    let end_ms = (end_ns - end_s * NS_PER_SEC) / NS_PER_MS;
+    if start_s == end_s {
+        format!("{start_s}s + {start_ms}\u{2013}{end_ms}ms")
+    } else {
+        format!("{start_s}s + {start_ms}ms \u{2013} {end_s}s + {end_ms}ms")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flamegraph::{ResourceSamples, test_stack};
+
+    /// Timestamps of a single resource, each of weight 1.
+    fn timestamps<const N: usize>(threads: [(&str, Vec<(u64, Stack)>); N]) -> Timestamps {
+        let threads = threads
+            .into_iter()
+            .map(|(thread, samples)| {
+                let samples = samples.into_iter().map(|(ts, stack)| (ts, 1, stack));
+                (thread.to_string(), samples.collect())
+            })
+            .collect();
+        HashMap::from([(
+            "app".to_string(),
+            ResourceSamples {
+                threads,
+                approximated: false,
+            },
+        )])
+    }
+
+    #[test]
+    fn range_graph_keeps_only_selected_cells() {
+        let (a, b) = (
+            test_stack(&["t1", "main", "a"]),
+            test_stack(&["t1", "main", "b"]),
+        );
+        let ms = |ms: u64| 5 * NS_PER_SEC + ms * 1_000_000;
+        let mut fs = FlamescopeTab::default();
+        fs.record_timestamps(&timestamps([(
+            "t1",
+            vec![
+                (ms(0), a.clone()),
+                (ms(150), a.clone()),
+                (ms(1050), b.clone()),
+                (ms(2500), b.clone()),
+            ],
+        )]));
+
+        fs.anchor = Some((0, 1));
+        (fs.cursor_col, fs.cursor_row) = (1, 0);
+        assert_eq!(fs.selection_value(), 2);
+        let range = fs.range_graph();
+        assert_eq!(range.samples, 2);
+        let main = range
+            .graph
+            .root
+            .find_path(&["t1".into(), "main".into()])
+            .unwrap();
+        assert_eq!(main.child_by_name("a").unwrap().total_value, 1);
+        assert_eq!(main.child_by_name("b").unwrap().total_value, 1);
+
+        fs.anchor = None;
+        fs.filter = Some("t2".to_string());
+        assert_eq!(fs.range_graph().samples, 0);
+    }
+
+    #[test]
+    fn spread_samples_count_their_weight() {
+        let a = test_stack(&["t1", "main", "a"]);
+        let mut fs = FlamescopeTab::default();
+        let threads = HashMap::from([("t1".to_string(), vec![(5 * NS_PER_SEC, 7, a)])]);
+        fs.record_timestamps(&HashMap::from([(
+            "app".to_string(),
+            ResourceSamples {
+                threads,
+                approximated: true,
+            },
+        )]));
+
+        let total = |fs: &FlamescopeTab| fs.visible_columns().iter().flatten().sum::<u64>();
+        assert_eq!(total(&fs), 7);
+        let range = fs.range_graph();
+        assert_eq!(range.samples, 7);
+        assert_eq!(range.graph.root.total_value, 7);
+
+        fs.set_resolution(20, 2);
+        assert_eq!(total(&fs), 7);
+    }
+
+    #[test]
+    fn resolution_change_rebuckets_samples() {
+        let a = test_stack(&["t1", "main"]);
+        let ms = |ms: u64| ms * 1_000_000;
+        let mut fs = FlamescopeTab::default();
+        fs.record_timestamps(&timestamps([(
+            "t1",
+            [0, 120, 1_030, 3_990].map(|t| (ms(t), a.clone())).to_vec(),
+        )]));
+        (fs.cursor_col, fs.cursor_row) = (1, 0);
+        assert_eq!(fs.visible_columns().len(), 4);
+
+        fs.set_resolution(20, 2);
+        assert_eq!(fs.visible_columns().len(), 2);
+        assert_eq!(fs.visible_columns()[0][0], 1);
+        assert_eq!(fs.visible_columns()[0][1], 1);
+        assert_eq!(fs.visible_columns()[0][10], 1);
+        assert_eq!(fs.visible_columns()[1][19], 1);
+        assert_eq!((fs.cursor_col, fs.cursor_row), (0, 10));
+        assert_eq!(
+            format_span(fs.cell_span((0, 10)).0, fs.cell_span((0, 10)).1),
+            "1s + 0\u{2013}100ms"
+        );
+        assert_eq!(
+            format_span(ms(900), ms(2_100)),
+            "0s + 900ms \u{2013} 2s + 100ms"
+        );
+        assert_eq!(format_span(ms(900), ms(1_000)), "0s + 900\u{2013}1000ms");
+    }
+
+    #[test]
+    fn retention_drops_old_columns_and_threads() {
+        let (a, b) = (test_stack(&["t1", "main"]), test_stack(&["t2", "main"]));
+        let secs = |s: u64| 100 * NS_PER_SEC + s * NS_PER_SEC;
+        let mut fs = FlamescopeTab {
+            retention_secs: Some(10),
+            ..FlamescopeTab::default()
+        };
+        fs.record_timestamps(&timestamps([
+            ("t1", vec![(secs(0), a.clone()), (secs(8), a.clone())]),
+            ("t2", vec![(secs(0), b.clone())]),
+        ]));
+        fs.cursor_col = 8;
+        fs.filter = Some("t2".to_string());
+
+        fs.record_timestamps(&timestamps([("t1", vec![(secs(15), a.clone())])]));
+        assert_eq!(fs.visible_columns().len(), 11);
+        assert_eq!(fs.cursor_col, 3);
+        assert_eq!(fs.cell_span((0, 0)).0, 5 * NS_PER_SEC);
+        assert_eq!(fs.thread_names(), ["t1"]);
+        assert_eq!(fs.filter, None);
+        assert_eq!(fs.timeline().unwrap().samples.len(), 2);
+        assert_eq!(fs.range_graph().samples, 1);
+    }
+
+    #[test]
+    fn baseline_follows_its_cells() {
+        let a = test_stack(&["t1", "main", "a"]);
+        let secs = |s: u64| 1_000 * NS_PER_SEC + s * NS_PER_SEC;
+        let mut fs = FlamescopeTab::default();
+        fs.record_timestamps(&timestamps([(
+            "t1",
+            (0..4).map(|s| (secs(s), a.clone())).collect(),
+        )]));
+        fs.anchor = Some((1, 0));
+        (fs.cursor_col, fs.cursor_row) = (2, 9);
+        fs.mark_baseline();
+        assert_eq!(fs.anchor, None);
+        assert_eq!(fs.baseline.as_ref().unwrap().range.samples, 2);
+        assert!(!fs.in_baseline((0, 9)));
+        assert!(fs.in_baseline((1, 0)) && fs.in_baseline((2, 9)));
+        assert!(!fs.in_baseline((3, 0)));
+
+        // Still the same seconds after columns are inserted in front.
+        fs.record_timestamps(&timestamps([("t1", vec![(secs(0) - 1, a.clone())])]));
+        assert!(!fs.in_baseline((1, 0)));
+        assert!(fs.in_baseline((2, 0)) && fs.in_baseline((3, 9)));
+    }
+
+    #[test]
+    fn stray_timestamps_stay_bounded() {
+        let a = test_stack(&["t1", "main"]);
+        let secs = |s: u64| 1_700_000_000 * NS_PER_SEC + s * NS_PER_SEC;
+        for retention_secs in [Some(DEFAULT_RETENTION_SECS), None] {
+            let mut fs = FlamescopeTab {
+                retention_secs,
+                ..FlamescopeTab::default()
+            };
+            fs.record_timestamps(&timestamps([("t1", vec![(secs(0), a.clone())])]));
+            fs.record_timestamps(&timestamps([("t1", vec![(secs(4), a.clone())])]));
+            fs.record_timestamps(&timestamps([("t1", vec![(0, a.clone())])]));
+            assert_eq!(fs.visible_columns().len(), 5);
+            assert_eq!(fs.column_unix_secs(0), Some(1_700_000_000));
+            assert_eq!(fs.timeline().unwrap().samples.len(), 2);
+
+            // A stray sample far ahead is dropped like one far behind.
+            fs.record_timestamps(&timestamps([("t1", vec![(u64::MAX / 2, a.clone())])]));
+            assert_eq!(fs.visible_columns().len(), 5);
+            assert_eq!(fs.timeline().unwrap().samples.len(), 2);
+            fs.record_timestamps(&timestamps([("t1", vec![(secs(5), a.clone())])]));
+            assert_eq!(fs.visible_columns().len(), 6);
+
+            // Only a clock that stays ahead restarts the timeline.
+            let ahead = (0..RESTART_AFTER as u64).map(|s| (u64::MAX / 2 + s, a.clone()));
+            fs.record_timestamps(&timestamps([("t1", ahead.collect())]));
+            assert_eq!(fs.visible_columns().len(), 1);
+            assert_eq!(fs.timeline().unwrap().samples.len(), 1);
+        }
+    }
+
+    #[test]
+    fn older_samples_rebase_the_epoch() {
+        let a = test_stack(&["t1", "main"]);
+        let ms = |ms: u64| 1_700_000_000 * NS_PER_SEC + ms * 1_000_000;
+        let mut fs = FlamescopeTab::default();
+        fs.record_timestamps(&timestamps([("t1", vec![(ms(10_000), a.clone())])]));
+        fs.anchor = Some((0, 0));
+        fs.record_timestamps(&timestamps([("t1", vec![(ms(7_500), a.clone())])]));
+
+        assert_eq!(fs.visible_columns().len(), 4);
+        assert_eq!(fs.visible_columns()[0][5], 1);
+        assert_eq!(fs.visible_columns()[3][0], 1);
+        assert_eq!(fs.anchor, Some((3, 0)));
+        assert_eq!(fs.column_unix_secs(0), Some(1_700_000_007));
+        assert_eq!(fs.cell_span((3, 0)).0, 3 * NS_PER_SEC);
+
+        // Another agent's clock doesn't stretch this timeline.
+        fs.record_timestamps(&HashMap::from([(
+            "other".to_string(),
+            ResourceSamples {
+                threads: HashMap::from([("t9".to_string(), vec![(ms(0), 1, a.clone())])]),
+                approximated: true,
+            },
+        )]));
+        assert!(!fs.is_approximated());
+        assert_eq!(fs.resource.as_deref(), Some("app"));
+        assert_eq!(fs.visible_columns().len(), 4);
+        fs.cycle_resource();
+        assert_eq!(fs.resource.as_deref(), Some("other"));
+        assert_eq!(fs.visible_columns().len(), 1);
+        assert_eq!(fs.thread_names(), ["t9"]);
+        assert!(fs.is_approximated());
+
+        // A resource that stopped reporting ages out with the others.
+        let later = ms(10_000) + DEFAULT_RETENTION_SECS * NS_PER_SEC;
+        fs.record_timestamps(&timestamps([("t1", vec![(later, a.clone())])]));
+        assert_eq!(fs.timelines.len(), 1);
+        assert_eq!(fs.resource.as_deref(), Some("app"));
+        assert_eq!(fs.thread_names(), ["t1"]);
+    }
+
+    #[test]
+    fn markers_bound_a_selection() {
+        let a = test_stack(&["t1", "main"]);
+        let ms = |ms: u64| 1_000 * NS_PER_SEC + ms * 1_000_000;
+        let mut fs = FlamescopeTab::default();
+        fs.record_timestamps(&timestamps([(
+            "t1",
+            (0..5).map(|s| (ms(s * 1_000), a.clone())).collect(),
+        )]));
+        for (at, label) in [(3_450, "load test end"), (500, "deploy"), (60_000, "later")] {
+            fs.add_marker(Marker {
+                unix_nano: ms(at),
+                label: label.to_string(),
+            });
+        }
+        assert_eq!(fs.markers[0].label, "deploy");
+        assert_eq!(fs.marker_cell(&fs.markers[2]), None);
+
+        fs.handle_key(KeyCode::Char('n').into());
+        assert_eq!((fs.cursor_col, fs.cursor_row), (0, 5));
+        fs.handle_key(KeyCode::Char(' ').into());
+        fs.handle_key(KeyCode::Char('n').into());
+        assert_eq!((fs.cursor_col, fs.cursor_row), (3, 4));
+        fs.handle_key(KeyCode::Char('n').into());
+        assert_eq!((fs.cursor_col, fs.cursor_row), (3, 4));
+        assert_eq!(fs.selection_value(), 3);
+        assert_eq!(
+            fs.column_markers(3)
+                .map(|m| m.label.as_str())
+                .collect::<Vec<_>>(),
+            ["load test end"]
+        );
+
+        fs.handle_key(KeyCode::Char('N').into());
+        assert_eq!((fs.cursor_col, fs.cursor_row), (0, 5));
+
+        // Markers past the retention window, and the oldest beyond the
+        // cap, are dropped.
+        let late = ms(500) + (DEFAULT_RETENTION_SECS + 1) * NS_PER_SEC;
+        for i in 0..MAX_MARKERS as u64 + 1 {
+            fs.add_marker(Marker {
+                unix_nano: late + i,
+                label: i.to_string(),
+            });
+        }
+        assert_eq!(fs.markers.len(), MAX_MARKERS);
+        assert_eq!(fs.markers[0].label, "1");
+    }
+
+    #[test]
+    fn color_scales_and_normalization() {
+        let (a, b) = (test_stack(&["t1", "main"]), test_stack(&["t2", "main"]));
+        let secs = |s: u64| 1_000 * NS_PER_SEC + s * NS_PER_SEC;
+        let mut hot: Vec<(u64, Stack)> = (0..100).map(|i| (secs(0) + i, b.clone())).collect();
+        hot.extend((1..100).map(|s| (secs(s), b.clone())));
+        let mut fs = FlamescopeTab::default();
+        fs.record_timestamps(&timestamps([
+            ("t1", (0..5).map(|s| (secs(s), a.clone())).collect()),
+            ("t2", hot),
+        ]));
+
+        assert_eq!(fs.color_max(), 101);
+        fs.handle_key(KeyCode::Char('c').into());
+        assert_eq!(fs.scale, HeatmapScale::Log);
+        fs.handle_key(KeyCode::Char('c').into());
+        // The single hot cell no longer sets the top color.
+        assert_eq!(fs.color_max(), 2);
+
+        fs.scale = HeatmapScale::Linear;
+        fs.filter = Some("t1".to_string());
+        assert_eq!(fs.color_max(), 1);
+        fs.handle_key(KeyCode::Char('g').into());
+        assert_eq!(fs.color_max(), 101);
+        assert_eq!(fs.visible_peak(), 1);
+
+        // New samples refresh the cached counts.
+        fs.record_timestamps(&timestamps([("t1", vec![(secs(2) + 1, a.clone())])]));
+        assert_eq!(fs.visible_peak(), 2);
+
+        for scale in HeatmapScale::ALL {
+            assert_eq!(scale.fraction(0, 100), 0.0);
+            assert_eq!(scale.fraction(100, 100), 1.0);
+            assert_eq!(scale.value_at(1.0, 100), 100);
+            assert_eq!(scale.value_at(scale.fraction(25, 100), 100), 25);
+        }
+        assert_eq!(HeatmapScale::Log.value_at(0.5, 99), 9);
+    }
+}
@@ -0,0 +1,149 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use crate::flamegraph::{FlameGraph, FunctionStats};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    SelfSamples,
+    TotalSamples,
+    Name,
+}
+
+impl SortColumn {
+    fn next(self) -> Self {
+        match self {
+            SortColumn::SelfSamples => SortColumn::TotalSamples,
+            SortColumn::TotalSamples => SortColumn::Name,
+            SortColumn::Name => SortColumn::SelfSamples,
+        }
+    }
+}
+
+pub struct FunctionsTab {
+    rows: Vec<FunctionStats>,
+    /// Indices into `rows` that pass the filter, in display order.
+    pub visible: Vec<usize>,
+    pub root_total: i64,
+    pub sort: SortColumn,
+    pub cursor: usize,
+    pub scroll: usize,
+    pub filter: String,
+    pub filtering: bool,
+}
+
+impl Default for FunctionsTab {
+    fn default() -> Self {
+        Self {
+            rows: Vec::new(),
+            visible: Vec::new(),
+            root_total: 0,
+            sort: SortColumn::SelfSamples,
+            cursor: 0,
+            scroll: 0,
+            filter: String::new(),
+            filtering: false,
+        }
+    }
+}
+
+impl FunctionsTab {
+    pub fn refresh(&mut self, graph: &FlameGraph) {
+        let selected = self.selected().map(|f| f.name.clone());
+        self.rows = graph.functions();
+        self.root_total = graph.root.total_value;
+        self.apply_filter();
+        if let Some(name) = selected {
+            self.cursor = self
+                .visible
+                .iter()
+                .position(|&i| self.rows[i].name == name)
+                .unwrap_or(self.cursor);
+        }
+        self.clamp_cursor();
+    }
+
+    pub fn row(&self, visible_idx: usize) -> Option<&FunctionStats> {
+        self.visible.get(visible_idx).map(|&i| &self.rows[i])
+    }
+
+    fn selected(&self) -> Option<&FunctionStats> {
+        self.row(self.cursor)
+    }
+
+    fn apply_filter(&mut self) {
+        let query = self.filter.to_lowercase();
+        self.visible = (0..self.rows.len())
+            .filter(|&i| query.is_empty() || self.rows[i].name.to_lowercase().contains(&query))
+            .collect();
+        let rows = &self.rows;
+        match self.sort {
+            SortColumn::SelfSamples => self.visible.sort_by(|&a, &b| {
+                rows[b]
+                    .self_value
+                    .cmp(&rows[a].self_value)
+                    .then(rows[b].total_value.cmp(&rows[a].total_value))
+            }),
+            SortColumn::TotalSamples => self.visible.sort_by(|&a, &b| {
+                rows[b]
+                    .total_value
+                    .cmp(&rows[a].total_value)
+                    .then(rows[b].self_value.cmp(&rows[a].self_value))
+            }),
+            SortColumn::Name => self
+                .visible
+                .sort_by(|&a, &b| rows[a].name.cmp(&rows[b].name)),
+        }
+    }
+
+    fn clamp_cursor(&mut self) {
+        self.cursor = self.cursor.min(self.visible.len().saturating_sub(1));
+    }
+
+    /// Returns the function to highlight in the flamegraph when a row is chosen.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> Option<String> {
+        if self.filtering {
+            self.handle_filter_key(key);
+            return None;
+        }
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.cursor + 1 < self.visible.len() {
+                    self.cursor += 1;
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.apply_filter();
+            }
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.apply_filter();
+                self.clamp_cursor();
+            }
+            KeyCode::Enter => return self.selected().map(|f| f.name.clone()),
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => self.filtering = false,
+            KeyCode::Esc => {
+                self.filtering = false;
+                self.filter.clear();
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) => self.filter.push(c),
+            _ => return,
+        }
+        self.cursor = 0;
+        self.apply_filter();
+    }
+}
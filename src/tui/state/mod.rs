@@ -1,28 +1,50 @@
 mod executables;
 mod flamegraph;
 mod flamescope;
+mod functions;
+mod threads;
 
 pub use executables::ExecutablesTab;
-pub use flamegraph::FlamegraphTab;
-pub use flamescope::FlamescopeTab;
+pub use flamegraph::{ColorScheme, FlamegraphTab, Recursion, format_clock};
+pub use flamescope::{DEFAULT_RETENTION_SECS, FlamescopeTab, HeatmapScale, format_span};
+pub use functions::{FunctionsTab, SortColumn};
+pub use threads::{RECENT_SECS, ThreadsTab};
 
 use std::path::PathBuf;
 
-use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent};
 
-use crate::storage::{ExecutableInfo, FileId};
+use crate::markers::Marker;
+use crate::storage::{ExecutableInfo, FileId, Snapshot};
+use crate::trigger::{Capture, Watcher};
 use crate::tui::event::Event;
+use flamegraph::{format_datetime, unix_secs};
+use threads::ThreadJump;
+
+/// Most trigger captures kept, each a window and its baseline.
+const MAX_CAPTURES: usize = 16;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ActiveTab {
     Flamegraph,
+    Functions,
     Flamescope,
+    Threads,
     Executables,
 }
 
 pub enum Action {
     LoadSymbols(PathBuf, Option<String>),
     RemoveSymbols(String, FileId),
+    SaveSnapshot(Snapshot),
+    /// Snapshots captured by a trigger, and the names of the older
+    /// captures dropped to make room for them.
+    SaveSnapshots {
+        snapshots: Vec<Snapshot>,
+        removed: Vec<String>,
+    },
+    RemoveSnapshot(String),
+    SetMouseCapture(bool),
     None,
 }
 
@@ -88,27 +110,53 @@ pub struct State {
     pub listen_addr: String,
     pub active_tab: ActiveTab,
     pub fg: FlamegraphTab,
+    pub functions: FunctionsTab,
     pub fs: FlamescopeTab,
+    pub threads: ThreadsTab,
     pub exe: ExecutablesTab,
+    pub triggers: Watcher,
+    /// Whether mouse events are captured, toggled with `m`.
+    pub mouse: bool,
 }
 
 impl State {
-    pub fn new(listen_addr: String, initial_exes: Vec<ExecutableInfo>) -> Self {
+    pub fn new(
+        listen_addr: String,
+        initial_exes: Vec<ExecutableInfo>,
+        initial_snapshots: Vec<Snapshot>,
+    ) -> Self {
         Self {
             running: true,
             listen_addr,
             active_tab: ActiveTab::Flamegraph,
-            fg: FlamegraphTab::default(),
+            fg: FlamegraphTab::from(initial_snapshots),
+            functions: FunctionsTab::default(),
             fs: FlamescopeTab::default(),
+            threads: ThreadsTab::default(),
             exe: ExecutablesTab::from(initial_exes),
+            triggers: Watcher::default(),
+            mouse: true,
         }
     }
 
     /// Central event handler: mutates state and returns an Action for side effects.
     pub fn handle_event(&mut self, event: Event) -> Action {
         match event {
-            Event::Tick | Event::Resize => Action::None,
+            Event::Tick => {
+                if self.fg.expire() && self.active_tab == ActiveTab::Functions {
+                    self.functions.refresh(&self.fg.functions_graph());
+                }
+                if !self.fg.frozen {
+                    self.threads.advance(unix_secs());
+                }
+                Action::None
+            }
+            Event::Resize => Action::None,
             Event::Key(key) => self.handle_key(key),
+            Event::Mouse(mouse) => {
+                self.handle_mouse(mouse);
+                Action::None
+            }
             Event::ProfileUpdate {
                 flamegraph,
                 samples,
@@ -116,8 +164,27 @@ impl State {
             } => {
                 if !self.fg.frozen {
                     self.fs.record_timestamps(&timestamps);
+                    self.threads.record(&flamegraph, &timestamps, unix_secs());
                 }
                 self.fg.merge(flamegraph, samples);
+                if self.active_tab == ActiveTab::Functions {
+                    self.functions.refresh(&self.fg.functions_graph());
+                }
+                let snapshots: Vec<Snapshot> = self
+                    .triggers
+                    .record(&timestamps)
+                    .into_iter()
+                    .flat_map(|capture| self.capture_snapshots(capture))
+                    .collect();
+                if snapshots.is_empty() {
+                    Action::None
+                } else {
+                    let removed = self.prune_captures();
+                    Action::SaveSnapshots { snapshots, removed }
+                }
+            }
+            Event::Marker(marker) => {
+                self.fs.add_marker(marker);
                 Action::None
             }
             Event::MappingsDiscovered(names) => {
@@ -132,7 +199,76 @@ impl State {
                 self.exe.handle_symbols_removed(name, error);
                 Action::None
             }
+            Event::SnapshotSaved { name, error } => {
+                self.fg.handle_snapshot_saved(name, error);
+                Action::None
+            }
+            Event::SnapshotRemoved { name, error } => {
+                self.fg.handle_snapshot_removed(name, error);
+                Action::None
+            }
+        }
+    }
+
+    /// Marks the window of a fired trigger and lists snapshots of it and of
+    /// its baseline.
+    fn capture_snapshots(&mut self, capture: Capture) -> [Snapshot; 2] {
+        let marker = Marker {
+            unix_nano: capture.start_secs * 1_000_000_000,
+            label: capture.spec.clone(),
+        };
+        self.fs.add_marker(marker.clone());
+        let at = format_datetime(capture.start_secs);
+        let name = format!("{} at {at}", capture.spec);
+        let len = capture.end_secs - capture.start_secs;
+        let snapshots = [
+            Snapshot {
+                name: name.clone(),
+                graph: capture.window.0,
+                samples: capture.window.1,
+                start_secs: capture.start_secs,
+                end_secs: capture.end_secs,
+                markers: vec![marker],
+            },
+            Snapshot {
+                name: format!("{name} (baseline)"),
+                graph: capture.baseline.0,
+                samples: capture.baseline.1,
+                start_secs: capture.start_secs.saturating_sub(len),
+                end_secs: capture.start_secs,
+                markers: Vec::new(),
+            },
+        ];
+        for snapshot in &snapshots {
+            self.fg.add_snapshot(snapshot.clone());
         }
+        snapshots
+    }
+
+    /// Unlists all but the [`MAX_CAPTURES`] most recent trigger captures,
+    /// returning the names of the snapshots dropped.
+    fn prune_captures(&mut self) -> Vec<String> {
+        let mut captures: Vec<&str> = self
+            .fg
+            .snapshots
+            .iter()
+            .filter_map(|s| capture_name(&s.name))
+            .collect();
+        captures.sort_by_key(|name| (name.rsplit_once(" at ").map(|(_, at)| at), *name));
+        captures.dedup();
+        let stale = captures.len().saturating_sub(MAX_CAPTURES);
+        let stale: Vec<String> = captures[..stale].iter().map(|s| s.to_string()).collect();
+        let removed: Vec<String> = self
+            .fg
+            .snapshots
+            .iter()
+            .filter(|s| capture_name(&s.name).is_some_and(|name| stale.iter().any(|n| n == name)))
+            .map(|s| s.name.clone())
+            .collect();
+        for name in &removed {
+            self.fg.remove_snapshot(name);
+        }
+        removed
     }
 
     fn handle_key(&mut self, key: KeyEvent) -> Action {
@@ -141,15 +277,19 @@ impl State {
             return Action::None;
         }
 
-        let overlay_active =
-            self.fg.search.active || self.fs.search.active || self.exe.path_input.active;
+        let overlay_active = self.overlay_active();
 
         if key.code == KeyCode::Tab && !overlay_active {
             self.active_tab = match self.active_tab {
-                ActiveTab::Flamegraph => ActiveTab::Flamescope,
-                ActiveTab::Flamescope => ActiveTab::Executables,
+                ActiveTab::Flamegraph => ActiveTab::Functions,
+                ActiveTab::Functions => ActiveTab::Flamescope,
+                ActiveTab::Flamescope => ActiveTab::Threads,
+                ActiveTab::Threads => ActiveTab::Executables,
                 ActiveTab::Executables => ActiveTab::Flamegraph,
             };
+            if self.active_tab == ActiveTab::Functions {
+                self.functions.refresh(&self.fg.functions_graph());
+            }
             return Action::None;
         }
 
@@ -158,10 +298,80 @@ impl State {
             return Action::None;
         }
 
+        if key.code == KeyCode::Char('M') && !overlay_active {
+            self.fg.cycle_demangle();
+            return Action::None;
+        }
+
+        if key.code == KeyCode::Char('m') && !overlay_active {
+            self.mouse = !self.mouse;
+            return Action::SetMouseCapture(self.mouse);
+        }
+
         match self.active_tab {
-            ActiveTab::Flamegraph => { self.fg.handle_key(key); Action::None }
-            ActiveTab::Flamescope => { self.fs.handle_key(key); Action::None }
+            ActiveTab::Flamegraph => self.fg.handle_key(key, &self.fs.markers),
+            ActiveTab::Functions => {
+                if let Some(name) = self.functions.handle_key(key) {
+                    self.fg.highlight_function(&name);
+                    self.active_tab = ActiveTab::Flamegraph;
+                }
+                Action::None
+            }
+            ActiveTab::Flamescope => {
+                if let Some(range) = self.fs.handle_key(key) {
+                    match self.fs.baseline {
+                        Some(ref baseline) => self.fg.compare_range(range, &baseline.range),
+                        None => self.fg.show_range(range),
+                    }
+                    self.active_tab = ActiveTab::Flamegraph;
+                }
+                Action::None
+            }
+            ActiveTab::Threads => {
+                match self.threads.handle_key(key) {
+                    Some(ThreadJump::Flamegraph(thread)) => {
+                        self.fg.show_thread(thread);
+                        self.active_tab = ActiveTab::Flamegraph;
+                    }
+                    Some(ThreadJump::Flamescope(thread)) => {
+                        self.fs.show_thread(thread);
+                        self.active_tab = ActiveTab::Flamescope;
+                    }
+                    None => {}
+                }
+                Action::None
+            }
             ActiveTab::Executables => self.exe.handle_key(key),
         }
     }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.overlay_active() {
+            return;
+        }
+        match self.active_tab {
+            ActiveTab::Flamegraph => self.fg.handle_mouse(mouse),
+            ActiveTab::Flamescope => self.fs.handle_mouse(mouse),
+            ActiveTab::Functions | ActiveTab::Threads | ActiveTab::Executables => {}
+        }
+    }
+
+    fn overlay_active(&self) -> bool {
+        self.fg.overlay_active()
+            || self.functions.filtering
+            || self.fs.search.active
+            || self.fs.marker_name.active
+            || self.exe.path_input.active
+    }
+}
+
+/// Name shared by the window and baseline snapshots of a trigger capture,
+/// `SPEC at YYYY-MM-DD HH:MM:SS`, or `None` for other snapshots.
+fn capture_name(name: &str) -> Option<&str> {
+    let name = name.strip_suffix(" (baseline)").unwrap_or(name);
+    let (_, at) = name.rsplit_once(" at ")?;
+    let shape = "0000-00-00 00:00:00";
+    let dated = at.len() == shape.len()
+        && (at.bytes().zip(shape.bytes())).all(|(b, s)| b == s || s == b'0' && b.is_ascii_digit());
+    dated.then_some(name)
 }
@@ -0,0 +1,279 @@
+use std::collections::{HashMap, VecDeque};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use crate::flamegraph::{FlameGraph, Timestamps};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+/// Seconds of history kept per thread.
+const HISTORY_SECS: usize = 300;
+/// Trailing seconds whose samples decide the row order.
+pub const RECENT_SECS: usize = 10;
+
+pub struct ThreadRow {
+    /// Thread name, or the group name with digit runs replaced by `*`.
+    pub name: String,
+    /// Busiest member of a group, the thread itself otherwise.
+    pub thread: String,
+    pub members: usize,
+    /// Samples per second, oldest first.
+    pub rates: Vec<u64>,
+    /// Samples in the last `RECENT_SECS` seconds.
+    pub recent: u64,
+}
+
+/// Where the selected thread should be opened.
+pub enum ThreadJump {
+    Flamegraph(String),
+    Flamescope(String),
+}
+
+/// Samples per second of every thread, by sample timestamp.
+#[derive(Default)]
+pub struct ThreadsTab {
+    /// Unix second of the last entry of every history.
+    newest_secs: u64,
+    /// Seconds of history recorded so far, at most `HISTORY_SECS`.
+    len: usize,
+    /// Samples per second of every thread with samples in the history,
+    /// `len` entries each, oldest first.
+    threads: HashMap<String, VecDeque<u64>>,
+    pub rows: Vec<ThreadRow>,
+    /// Whether threads differing only in numbers share a row.
+    pub grouped: bool,
+    pub cursor: usize,
+    pub scroll: usize,
+}
+
+impl ThreadsTab {
+    /// Adds the samples of a profile update to the seconds of their
+    /// timestamps. Samples ahead of the local clock count in the newest
+    /// second, and samples without timestamps in the second they arrived.
+    pub fn record(&mut self, graph: &FlameGraph, timestamps: &Timestamps, now: u64) {
+        self.advance(now);
+        let mut timestamped: HashMap<&str, u64> = HashMap::new();
+        for (thread, samples) in timestamps.values().flat_map(|r| &r.threads) {
+            for &(ts, weight, _) in samples {
+                self.add(thread, ts / NS_PER_SEC, weight);
+                *timestamped.entry(thread).or_default() += weight;
+            }
+        }
+        for thread in &graph.root.children {
+            let total = thread.total_value.max(0) as u64;
+            let rest =
+                total.saturating_sub(timestamped.get(thread.name.as_str()).map_or(0, |&n| n));
+            if rest > 0 {
+                self.add(&thread.name, self.newest_secs, rest);
+            }
+        }
+        self.refresh();
+    }
+
+    fn add(&mut self, thread: &str, secs: u64, samples: u64) {
+        let age = self.newest_secs.saturating_sub(secs) as usize;
+        if age >= self.len {
+            return;
+        }
+        let len = self.len;
+        let rates = match self.threads.get_mut(thread) {
+            Some(rates) => rates,
+            None => self
+                .threads
+                .entry(thread.to_string())
+                .or_insert_with(|| vec![0; len].into()),
+        };
+        rates[len - 1 - age] += samples;
+    }
+
+    /// Moves the history forward to `now`, so that idle threads decay even
+    /// when no profiles arrive.
+    pub fn advance(&mut self, now: u64) {
+        if self.len == 0 {
+            self.newest_secs = now;
+            self.len = 1;
+        }
+        let elapsed = now.saturating_sub(self.newest_secs) as usize;
+        if elapsed == 0 {
+            return;
+        }
+        let shift = elapsed.min(HISTORY_SECS);
+        self.len = (self.len + shift).min(HISTORY_SECS);
+        for rates in self.threads.values_mut() {
+            rates.extend(std::iter::repeat_n(0, shift));
+            rates.drain(..rates.len() - self.len);
+        }
+        self.threads.retain(|_, rates| rates.iter().any(|&n| n > 0));
+        self.newest_secs = now;
+        self.refresh();
+    }
+
+    pub fn history_secs(&self) -> usize {
+        self.len
+    }
+
+    fn refresh(&mut self) {
+        let selected = self.rows.get(self.cursor).map(|r| r.name.clone());
+        let len = self.len;
+
+        let mut rows: HashMap<String, ThreadRow> = HashMap::new();
+        let mut busiest: HashMap<String, (u64, u64)> = HashMap::new();
+        for (thread, rates) in &self.threads {
+            let name = if self.grouped {
+                group_name(thread)
+            } else {
+                thread.clone()
+            };
+            let recent: u64 = rates.iter().rev().take(RECENT_SECS).sum();
+            let total: u64 = rates.iter().sum();
+            let row = rows.entry(name.clone()).or_insert_with(|| ThreadRow {
+                name: name.clone(),
+                thread: thread.clone(),
+                members: 0,
+                rates: vec![0; len],
+                recent: 0,
+            });
+            row.members += 1;
+            row.recent += recent;
+            for (sum, rate) in row.rates.iter_mut().zip(rates) {
+                *sum += rate;
+            }
+            match busiest.get(&name) {
+                Some(&best) if best >= (recent, total) => {}
+                _ => {
+                    busiest.insert(name, (recent, total));
+                    row.thread = thread.clone();
+                }
+            }
+        }
+
+        self.rows = rows.into_values().collect();
+        self.rows.sort_by(|a, b| {
+            b.recent
+                .cmp(&a.recent)
+                .then_with(|| b.rates.iter().sum::<u64>().cmp(&a.rates.iter().sum()))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        if let Some(name) = selected {
+            self.cursor = self
+                .rows
+                .iter()
+                .position(|r| r.name == name)
+                .unwrap_or(self.cursor);
+        }
+        self.cursor = self.cursor.min(self.rows.len().saturating_sub(1));
+    }
+
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> Option<ThreadJump> {
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.cursor + 1 < self.rows.len() {
+                    self.cursor += 1;
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            KeyCode::Char('g') => {
+                self.grouped = !self.grouped;
+                self.refresh();
+            }
+            KeyCode::Enter => {
+                return self
+                    .rows
+                    .get(self.cursor)
+                    .map(|r| ThreadJump::Flamegraph(r.thread.clone()));
+            }
+            KeyCode::Char('f') => {
+                return self
+                    .rows
+                    .get(self.cursor)
+                    .map(|r| ThreadJump::Flamescope(r.thread.clone()));
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// `worker-12` and `worker-3` both become `worker-*`.
+fn group_name(thread: &str) -> String {
+    let mut name = String::with_capacity(thread.len());
+    for c in thread.chars() {
+        if !c.is_ascii_digit() {
+            name.push(c);
+        } else if !name.ends_with('*') {
+            name.push('*');
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flamegraph::{ResourceSamples, test_stack};
+
+    fn graph(threads: &[(&str, i64)]) -> FlameGraph {
+        let mut graph = FlameGraph::new();
+        for &(thread, samples) in threads {
+            graph.add_stack(&test_stack(&[thread, "main"]), samples);
+        }
+        graph
+    }
+
+    #[test]
+    fn rows_sorted_by_recent_activity() {
+        let mut tab = ThreadsTab::default();
+        let none = Timestamps::new();
+        tab.record(&graph(&[("worker-1", 50), ("io", 5)]), &none, 1_000);
+        tab.record(&graph(&[("worker-2", 3), ("io", 4)]), &none, 1_020);
+        tab.record(&graph(&[("worker-2", 2)]), &none, 1_021);
+
+        let names: Vec<&str> = tab.rows.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["worker-2", "io", "worker-1"]);
+        assert_eq!(tab.history_secs(), 22);
+        assert_eq!(tab.rows[0].rates[20..], [3, 2]);
+
+        tab.handle_key(KeyCode::Char('g').into());
+        assert_eq!(tab.rows[0].name, "worker-*");
+        assert_eq!(tab.rows[0].members, 2);
+        assert_eq!(tab.rows[0].recent, 5);
+        assert_eq!(tab.rows[0].thread, "worker-2");
+        tab.cursor = 0;
+        assert!(matches!(
+            tab.handle_key(KeyCode::Char('f').into()),
+            Some(ThreadJump::Flamescope(thread)) if thread == "worker-2"
+        ));
+    }
+
+    #[test]
+    fn samples_binned_by_timestamp() {
+        let mut tab = ThreadsTab::default();
+        tab.advance(1_000);
+        let secs = |secs: u64| secs * NS_PER_SEC;
+        let samples = vec![
+            (secs(1_005), 1, test_stack(&["app", "main"])),
+            (secs(1_007), 2, test_stack(&["app", "main"])),
+            // Ahead of the local clock.
+            (secs(1_020), 1, test_stack(&["app", "main"])),
+            // Older than the history.
+            (secs(100), 1, test_stack(&["app", "main"])),
+        ];
+        let timestamps = Timestamps::from([(
+            "api".to_string(),
+            ResourceSamples {
+                threads: HashMap::from([("app".to_string(), samples)]),
+                approximated: false,
+            },
+        )]);
+        // One more sample of the graph carries no timestamp.
+        tab.record(&graph(&[("app", 6)]), &timestamps, 1_010);
+
+        assert_eq!(tab.history_secs(), 11);
+        assert_eq!(tab.rows[0].rates, [0, 0, 0, 0, 0, 1, 0, 2, 0, 0, 2]);
+
+        tab.advance(1_000 + HISTORY_SECS as u64 + 20);
+        assert!(tab.rows.is_empty());
+        assert_eq!(tab.history_secs(), HISTORY_SECS);
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use ratatui::{
     Frame,
     buffer::Buffer,
@@ -8,14 +10,27 @@ use ratatui::{
 };
 
 use super::flamescope_layout::FlamescopeLayout;
-use super::state::{ActiveTab, ExecutablesTab, FlamegraphTab, FlamescopeTab, SearchOverlay, State};
-use crate::flamegraph::{cursor_frame_rect, layout_frames};
+
+use super::state::{
+    ActiveTab, ColorScheme, ExecutablesTab, FlamegraphTab, FlamescopeTab, FunctionsTab,
+    HeatmapScale, RECENT_SECS, Recursion, SearchOverlay, SortColumn, State, ThreadsTab,
+    format_clock, format_span,
+};
+use crate::demangle::{self, Demangle};
+use crate::flamegraph::{
+    Butterfly, FilterKind, FlameNode, FrameRect, FrameType, cursor_frame_rect, layout_frames,
+    package,
+};
 
 const BG: Color = Color::Rgb(16, 16, 22);
 const ACCENT: Color = Color::Rgb(59, 130, 246);
 const DIM: Color = Color::Rgb(70, 70, 85);
 const BRIGHT: Color = Color::Rgb(220, 220, 235);
 const SEP_COLOR: Color = Color::Rgb(35, 35, 45);
+const HIGHLIGHT: Color = Color::Rgb(236, 72, 153);
+const MARKER: Color = Color::Rgb(45, 212, 191);
+/// Most entries in the color legend of the detail bar.
+const LEGEND_ENTRIES: usize = 6;
 
 pub fn render(state: &mut State, frame: &mut Frame) {
     let area = frame.area();
@@ -40,24 +55,80 @@ pub fn render(state: &mut State, frame: &mut Frame) {
 
     match state.active_tab {
         ActiveTab::Flamegraph => {
-            render_detail_bar(&state.fg, frame, chunks[1]);
-            render_flamegraph(&mut state.fg, frame, chunks[2]);
+            let detail_area = render_color_legend(&mut state.fg, frame, chunks[1]);
+            render_detail_bar(&state.fg, frame, detail_area);
+            let graph_area = if state.fg.filters.is_empty() {
+                chunks[2]
+            } else {
+                let split = Layout::new(
+                    Direction::Vertical,
+                    [Constraint::Length(1), Constraint::Min(0)],
+                )
+                .split(chunks[2]);
+                render_filter_bar(&state.fg, frame, split[0]);
+                split[1]
+            };
+            match state.fg.butterfly {
+                Some(ref b) => render_butterfly(
+                    b,
+                    state.fg.butterfly_scroll,
+                    state.fg.colors,
+                    state.fg.demangle,
+                    frame,
+                    graph_area,
+                ),
+                None => render_flamegraph(&mut state.fg, frame, graph_area),
+            }
             render_keyhints(
-                state.fg.search.active,
-                FLAMEGRAPH_KEYS,
-                SEARCH_KEYS,
+                state.fg.overlay_active(),
+                if state.fg.butterfly.is_some() {
+                    BUTTERFLY_KEYS
+                } else {
+                    FLAMEGRAPH_KEYS
+                },
+                if state.fg.snapshot_list.active {
+                    SNAPSHOT_LIST_KEYS
+                } else {
+                    SEARCH_KEYS
+                },
                 frame,
                 chunks[3],
             );
-            if state.fg.search.active {
-                render_search_overlay(&state.fg.search, frame, chunks[2]);
+            if state.fg.thread_search.active {
+                render_search_overlay(&state.fg.thread_search, frame, chunks[2]);
+            }
+            if state.fg.frame_search.active {
+                render_frame_search_overlay(&state.fg, frame, chunks[2]);
+            }
+            if state.fg.filter_input.active {
+                render_filter_input_overlay(&state.fg, frame, chunks[2]);
+            }
+            if state.fg.type_list.active {
+                render_type_list_overlay(&state.fg.type_list, frame, chunks[2]);
+            }
+            if state.fg.snapshot_name.active {
+                render_snapshot_name_overlay(&state.fg.snapshot_name, frame, chunks[2]);
             }
+            if state.fg.snapshot_list.active {
+                render_snapshot_list_overlay(&state.fg.snapshot_list, frame, chunks[2]);
+            }
+        }
+        ActiveTab::Functions => {
+            render_functions_status_bar(&state.functions, frame, chunks[1]);
+            render_functions_table(&mut state.functions, state.fg.demangle, frame, chunks[2]);
+            render_keyhints(
+                state.functions.filtering,
+                FUNCTIONS_KEYS,
+                FILTER_KEYS,
+                frame,
+                chunks[3],
+            );
         }
         ActiveTab::Flamescope => {
-            render_flamescope_detail_bar(&state.fs, frame, chunks[1]);
+            render_flamescope_detail_bar(&mut state.fs, frame, chunks[1]);
             render_flamescope(&mut state.fs, frame, chunks[2]);
             render_keyhints(
-                state.fs.search.active,
+                state.fs.search.active || state.fs.marker_name.active,
                 FLAMESCOPE_KEYS,
                 SEARCH_KEYS,
                 frame,
@@ -66,6 +137,14 @@ pub fn render(state: &mut State, frame: &mut Frame) {
             if state.fs.search.active {
                 render_search_overlay(&state.fs.search, frame, chunks[2]);
             }
+            if state.fs.marker_name.active {
+                render_marker_name_overlay(&state.fs.marker_name, frame, chunks[2]);
+            }
+        }
+        ActiveTab::Threads => {
+            render_threads_status_bar(&state.threads, frame, chunks[1]);
+            render_threads(&mut state.threads, frame, chunks[2]);
+            render_keyhints(false, THREADS_KEYS, &[], frame, chunks[3]);
         }
         ActiveTab::Executables => {
             render_exe_status_bar(state.exe.status.as_deref(), frame, chunks[1]);
@@ -212,7 +291,7 @@ fn center_str(buf: &mut Buffer, area: Rect, y: u16, text: &str, style: Style) {
 fn render_header(state: &State, frame: &mut Frame, area: Rect) {
     let sep = " │ ".fg(Color::Rgb(55, 55, 65));
 
-    let left_spans: Vec<Span> = vec![
+    let mut left_spans: Vec<Span> = vec![
         Span::styled(" ◆ ", Style::default().fg(ACCENT)),
         Span::styled(
             "eprofiler-tui",
@@ -225,10 +304,38 @@ fn render_header(state: &State, frame: &mut Frame, area: Rect) {
         ),
         sep.clone(),
         format!("{} profiles", state.fg.profiles_received).fg(Color::Rgb(110, 110, 130)),
-        sep,
+        sep.clone(),
         format!("{} samples", format_count(state.fg.samples_received))
             .fg(Color::Rgb(110, 110, 130)),
+        sep.clone(),
+        match state.fg.window_secs {
+            Some(secs) => format!("window {}", format_secs(secs)).fg(ACCENT),
+            None => "all-time".fg(Color::Rgb(110, 110, 130)),
+        },
     ];
+    if state.fg.pruned_nodes > 0 {
+        left_spans.push(sep);
+        left_spans.push(
+            format!(
+                "pruned {} nodes / {} samples",
+                format_count(state.fg.pruned_nodes),
+                format_count(state.fg.pruned_samples)
+            )
+            .fg(Color::Rgb(234, 179, 8)),
+        );
+    }
+    if let Some((ref spec, secs)) = state.triggers.last_capture {
+        left_spans.push(" │ ".fg(Color::Rgb(55, 55, 65)));
+        left_spans.push(
+            format!(
+                "⚡ {} captured, last: {spec} at {}",
+                state.triggers.captures,
+                format_clock(secs)
+            )
+            .fg(HIGHLIGHT)
+            .bold(),
+        );
+    }
     frame.render_widget(Paragraph::new(Line::from(left_spans)), area);
 
     let buf = frame.buffer_mut();
@@ -257,7 +364,9 @@ fn render_header(state: &State, frame: &mut Frame, area: Rect) {
 
     let tabs: &[(&str, ActiveTab)] = &[
         ("Flamegraph", ActiveTab::Flamegraph),
+        ("Functions", ActiveTab::Functions),
         ("Flamescope", ActiveTab::Flamescope),
+        ("Threads", ActiveTab::Threads),
         ("Executables", ActiveTab::Executables),
     ];
     let tabs_width: usize =
@@ -303,17 +412,225 @@ fn render_search_overlay(search: &SearchOverlay, frame: &mut Frame, area: Rect)
     );
 }
 
+fn render_frame_search_overlay(fg: &FlamegraphTab, frame: &mut Frame, area: Rect) {
+    let hint = match fg.highlight {
+        Some(ref h) => format!("{} matching frames", h.count()),
+        None => "regex or text, matched against frame names".to_string(),
+    };
+    render_overlay(
+        frame,
+        area,
+        &OverlayProps {
+            title: " search frames ",
+            input: &fg.frame_search.input,
+            items: &[],
+            cursor: 0,
+            border_color: HIGHLIGHT,
+            max_visible: 1,
+            empty_hint: &hint,
+            popup_width: 50,
+        },
+    );
+}
+
+fn render_filter_input_overlay(fg: &FlamegraphTab, frame: &mut Frame, area: Rect) {
+    let title = format!(" {} frames matching ", fg.filter_kind.label());
+    render_overlay(
+        frame,
+        area,
+        &OverlayProps {
+            title: &title,
+            input: &fg.filter_input.input,
+            items: &[],
+            cursor: 0,
+            border_color: filter_color(fg.filter_kind),
+            max_visible: 1,
+            empty_hint: "regex matched against frame names",
+            popup_width: 50,
+        },
+    );
+}
+
+fn render_type_list_overlay(list: &SearchOverlay, frame: &mut Frame, area: Rect) {
+    let items: Vec<&str> = list.matches.iter().map(String::as_str).collect();
+    render_overlay(
+        frame,
+        area,
+        &OverlayProps {
+            title: " frame types ",
+            input: &list.input,
+            items: &items,
+            cursor: list.cursor,
+            border_color: filter_color(FilterKind::Hide),
+            max_visible: 8,
+            empty_hint: "no matching frame types",
+            popup_width: 40,
+        },
+    );
+}
+
+fn render_snapshot_name_overlay(input: &SearchOverlay, frame: &mut Frame, area: Rect) {
+    render_overlay(
+        frame,
+        area,
+        &OverlayProps {
+            title: " snapshot name ",
+            input: &input.input,
+            items: &[],
+            cursor: 0,
+            border_color: ACCENT,
+            max_visible: 1,
+            empty_hint: "Enter saves the live graph under this name",
+            popup_width: 50,
+        },
+    );
+}
+
+fn render_marker_name_overlay(input: &SearchOverlay, frame: &mut Frame, area: Rect) {
+    render_overlay(
+        frame,
+        area,
+        &OverlayProps {
+            title: " marker label ",
+            input: &input.input,
+            items: &[],
+            cursor: 0,
+            border_color: MARKER,
+            max_visible: 1,
+            empty_hint: "Enter drops a marker at the current time",
+            popup_width: 50,
+        },
+    );
+}
+
+fn render_snapshot_list_overlay(list: &SearchOverlay, frame: &mut Frame, area: Rect) {
+    let items: Vec<&str> = list.matches.iter().map(String::as_str).collect();
+    render_overlay(
+        frame,
+        area,
+        &OverlayProps {
+            title: " snapshots ",
+            input: &list.input,
+            items: &items,
+            cursor: list.cursor,
+            border_color: ACCENT,
+            max_visible: 8,
+            empty_hint: "no matches",
+            popup_width: 50,
+        },
+    );
+}
+
 fn render_detail_bar(fg: &FlamegraphTab, frame: &mut Frame, area: Rect) {
     let sel = &fg.selection;
-    if sel.name.is_empty() && fg.zoom_path.is_empty() {
-        return;
-    }
-
     let sep = " │ ".fg(Color::Rgb(55, 55, 65));
-    let root_total = fg.graph.root.follow_path(&fg.zoom_path).total_value;
+    let root_total = fg.view().root.follow_path(&fg.zoom_path).total_value;
 
     let mut spans: Vec<Span> = Vec::new();
 
+    if let Some(snap) = fg.snapshot() {
+        spans.push(
+            format!(
+                " snapshot: {} ({} samples, {}\u{2013}{} UTC) ",
+                snap.name,
+                format_count(snap.samples),
+                format_clock(snap.start_secs),
+                format_clock(snap.end_secs),
+            )
+            .fg(Color::Rgb(34, 197, 94))
+            .bold(),
+        );
+        spans.push(sep.clone());
+        if !snap.markers.is_empty() {
+            let markers: Vec<String> = snap
+                .markers
+                .iter()
+                .map(|m| format!("{} {}", format_clock(m.unix_secs()), m.label))
+                .collect();
+            spans.push(format!(" ◆ {} ", markers.join(", ")).fg(MARKER));
+            spans.push(sep.clone());
+        }
+    }
+
+    if let Some(ref range) = fg.range {
+        spans.push(
+            format!(
+                " range: {} ({} samples) ",
+                range.label,
+                format_count(range.samples)
+            )
+            .fg(Color::Rgb(34, 197, 94))
+            .bold(),
+        );
+        spans.push(sep.clone());
+    }
+
+    if fg.inverted {
+        spans.push(" inverted ".fg(Color::Rgb(168, 85, 247)).bold());
+        spans.push(sep.clone());
+    }
+
+    if fg.demangle != Demangle::default() {
+        spans.push(format!(" symbols: {} ", fg.demangle.label()).fg(DIM).bold());
+        spans.push(sep.clone());
+    }
+
+    let folded = match fg.recursion {
+        Recursion::Expanded => None,
+        Recursion::Direct => Some(" recursion folded "),
+        Recursion::Cycles => Some(" recursion + cycles folded "),
+    };
+    if let Some(label) = folded {
+        spans.push(label.fg(Color::Rgb(20, 184, 166)).bold());
+        spans.push(sep.clone());
+    }
+
+    if fg.baseline().is_some() {
+        let label = match fg.baseline_range {
+            Some(ref range) => format!(" diff vs {range} "),
+            None => " diff ".to_string(),
+        };
+        spans.push(label.fg(Color::Rgb(239, 68, 68)).bold());
+        spans.push(sep.clone());
+    }
+
+    if let Some(ref b) = fg.butterfly {
+        spans.push(
+            format!(
+                " callers/callees: {} ({} samples) ",
+                truncate(&demangle::label(&b.name, fg.demangle), 40),
+                format_count(b.callers.total_value.max(0) as u64)
+            )
+            .fg(HIGHLIGHT)
+            .bold(),
+        );
+        spans.push(sep.clone());
+    }
+
+    if let Some(ref h) = fg.highlight {
+        let total = fg.zoom_root().total_value;
+        let position = if h.count() == 0 {
+            "no matches".to_string()
+        } else {
+            format!("{}/{}", h.cursor + 1, h.count())
+        };
+        spans.push(
+            format!(
+                " match: {} ({}, {:.1}%) ",
+                truncate(&demangle::label(&h.label, fg.demangle), 30),
+                position,
+                if total > 0 {
+                    h.matched_value as f64 / total as f64 * 100.0
+                } else {
+                    0.0
+                }
+            )
+            .fg(HIGHLIGHT)
+            .bold(),
+        );
+        spans.push(sep.clone());
+    }
+
     if !fg.zoom_path.is_empty() {
         spans.push(
             format!(
@@ -339,45 +656,147 @@ fn render_detail_bar(fg: &FlamegraphTab, frame: &mut Frame, area: Rect) {
 
         spans.push(" ▸ ".fg(ACCENT).bold());
         spans.push(Span::styled(
-            truncate(&sel.name, 40),
+            truncate(&demangle::label(&sel.name, fg.demangle), 40),
             Style::default().fg(BRIGHT).add_modifier(Modifier::BOLD),
         ));
         spans.push(sep.clone());
-        spans.push("self: ".fg(DIM));
-        spans.push(
-            format!(
-                "{} ({:.1}%)",
-                format_count(sel.self_value as u64),
-                pct(sel.self_value)
-            )
-            .fg(Color::Rgb(249, 115, 22)),
-        );
+        if let Some(before) = sel.baseline_pct {
+            let delta = sel.pct - before;
+            spans.push("before: ".fg(DIM));
+            spans.push(format!("{before:.1}%").fg(Color::Rgb(130, 130, 150)));
+            spans.push(sep.clone());
+            spans.push("after: ".fg(DIM));
+            spans.push(format!("{:.1}%", sel.pct).fg(BRIGHT));
+            spans.push(sep.clone());
+            spans.push("delta: ".fg(DIM));
+            spans.push(format!("{delta:+.2}%").fg(diff_color(delta, delta.abs())));
+        } else {
+            spans.push("self: ".fg(DIM));
+            spans.push(
+                format!(
+                    "{} ({:.1}%)",
+                    format_count(sel.self_value as u64),
+                    pct(sel.self_value)
+                )
+                .fg(Color::Rgb(249, 115, 22)),
+            );
+            spans.push(sep.clone());
+            spans.push("total: ".fg(DIM));
+            spans.push(
+                format!(
+                    "{} ({:.1}%)",
+                    format_count(sel.total_value as u64),
+                    pct(sel.total_value)
+                )
+                .fg(Color::Rgb(234, 179, 8)),
+            );
+        }
         spans.push(sep.clone());
-        spans.push("total: ".fg(DIM));
-        spans.push(
-            format!(
-                "{} ({:.1}%)",
-                format_count(sel.total_value as u64),
-                pct(sel.total_value)
-            )
-            .fg(Color::Rgb(234, 179, 8)),
-        );
-        spans.push(sep);
         spans.push("depth: ".fg(DIM));
         spans.push(sel.depth.to_string().fg(Color::Rgb(130, 130, 150)));
+        if sel.folded_depth > 0 {
+            spans.push(sep.clone());
+            spans.push("recursion depth: ".fg(DIM));
+            spans.push(
+                (sel.folded_depth + 1)
+                    .to_string()
+                    .fg(Color::Rgb(20, 184, 166)),
+            );
+        }
+    }
+
+    if let Some(ref status) = fg.status {
+        let color = if status.starts_with("Error") {
+            Color::Rgb(239, 68, 68)
+        } else {
+            Color::Rgb(130, 130, 150)
+        };
+        spans.push(sep);
+        spans.push(status.clone().fg(color));
+    }
+
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// Draws the legend of the color scheme at the right end of the detail bar
+/// and returns the area left for the bar itself.
+fn render_color_legend(fg: &mut FlamegraphTab, frame: &mut Frame, area: Rect) -> Rect {
+    if fg.colors == ColorScheme::Thread {
+        return area;
+    }
+    let (colors, mode) = (fg.colors, fg.demangle);
+    let entries = fg.legend(|root| {
+        let mut totals: HashMap<String, (i64, Color)> = HashMap::new();
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            stack.extend(&node.children);
+            if node.self_value > 0
+                && let Some((key, color)) =
+                    scheme_entry(colors, &node.name, node.frame_type, &node.module, mode)
+            {
+                totals.entry(key).or_insert((0, color)).0 += node.self_value;
+            }
+        }
+        let mut entries: Vec<_> = totals.into_iter().collect();
+        entries.sort_by(|(ka, (a, _)), (kb, (b, _))| b.cmp(a).then(ka.cmp(kb)));
+        entries
+            .into_iter()
+            .take(LEGEND_ENTRIES)
+            .map(|(key, (_, color))| (key, color))
+            .collect()
+    });
+
+    let mut spans: Vec<Span> = vec![format!(" colors: {} ", colors.label()).fg(DIM).bold()];
+    for (key, color) in entries {
+        spans.push("■ ".fg(*color));
+        spans.push(format!("{} ", truncate(key, 20)).fg(Color::Rgb(130, 130, 150)));
     }
+    let legend = Line::from(spans);
+    let width = (legend.width() as u16).min(area.width / 2);
+    let split = Layout::new(
+        Direction::Horizontal,
+        [Constraint::Min(0), Constraint::Length(width)],
+    )
+    .split(area);
+    frame.render_widget(Paragraph::new(legend), split[1]);
+    split[0]
+}
 
+fn render_filter_bar(fg: &FlamegraphTab, frame: &mut Frame, area: Rect) {
+    let mut spans: Vec<Span> = vec![" filters ".fg(DIM)];
+    for (i, filter) in fg.filters.iter().enumerate() {
+        let style = if filter.enabled {
+            Style::new().fg(filter_color(filter.kind)).bold()
+        } else {
+            Style::new().fg(DIM).add_modifier(Modifier::CROSSED_OUT)
+        };
+        spans.push(format!(" {} ", i + 1).fg(BRIGHT));
+        spans.push(Span::styled(
+            format!("{}:{} ", filter.kind.label(), truncate(&filter.pattern, 30)),
+            style,
+        ));
+    }
     frame.render_widget(Paragraph::new(Line::from(spans)), area);
 }
 
+fn filter_color(kind: FilterKind) -> Color {
+    match kind {
+        FilterKind::Focus => Color::Rgb(34, 197, 94),
+        FilterKind::Ignore => Color::Rgb(239, 68, 68),
+        FilterKind::Hide => Color::Rgb(245, 166, 35),
+    }
+}
+
 fn render_flamegraph(fg: &mut FlamegraphTab, frame: &mut Frame, area: Rect) {
     let buf = frame.buffer_mut();
+    fg.graph_area = area;
 
     if area.width < 4 || area.height < 2 {
         return;
     }
 
-    let zoom_root = fg.graph.root.follow_path(&fg.zoom_path);
+    let view = fg.view();
+    let zoom_root = view.root.follow_path(&fg.zoom_path);
     if zoom_root.total_value <= 0 {
         render_empty_fg(buf, area);
         return;
@@ -386,12 +805,47 @@ fn render_flamegraph(fg: &mut FlamegraphTab, frame: &mut Frame, area: Rect) {
     let forced_palette = fg
         .zoom_path
         .first()
-        .and_then(|name| fg.graph.root.child_position(name));
+        .and_then(|name| view.root.child_position(name));
 
-    let frames = layout_frames(zoom_root, area.width, forced_palette);
+    let empty = FlameNode::new(String::new());
+    let baseline = fg
+        .baseline()
+        .map(|b| b.root.find_path(&fg.zoom_path).unwrap_or(&empty));
+    let baseline_total = baseline.map_or(0, |b| b.total_value);
+
+    let frames = layout_frames(zoom_root, baseline, area.width, forced_palette);
     let max_depth = frames.iter().map(|f| f.depth).max().unwrap_or(0);
     let viewport_height = area.height as usize;
     let root_total = zoom_root.total_value;
+    let cursor_rect = cursor_frame_rect(
+        zoom_root,
+        baseline,
+        &fg.cursor_path,
+        area.width,
+        forced_palette,
+    );
+    let hover_path = fg.hover.and_then(|pos| fg.path_at(pos));
+    let hover_rect = hover_path
+        .as_ref()
+        .and_then(|path| cursor_frame_rect(zoom_root, baseline, path, area.width, forced_palette));
+    let folded_depth = zoom_root
+        .follow_indices(hover_path.as_deref().unwrap_or(&fg.cursor_path))
+        .folded_depth;
+    let share = |value: i64, total: i64| {
+        if total > 0 {
+            value as f64 / total as f64
+        } else {
+            0.0
+        }
+    };
+    let deltas: Vec<Option<f64>> = frames
+        .iter()
+        .map(|fr| {
+            fr.baseline_value
+                .map(|b| share(fr.total_value, root_total) - share(b, baseline_total))
+        })
+        .collect();
+    let max_delta = deltas.iter().flatten().fold(0.0f64, |m, d| m.max(d.abs()));
 
     let cursor_depth = fg.cursor_path.len();
     if viewport_height > 0 {
@@ -406,9 +860,7 @@ fn render_flamegraph(fg: &mut FlamegraphTab, frame: &mut Frame, area: Rect) {
         .scroll_y
         .min(max_depth.saturating_sub(viewport_height.saturating_sub(1)));
 
-    let cursor_rect = cursor_frame_rect(zoom_root, &fg.cursor_path, area.width, forced_palette);
-
-    if let Some(ref cr) = cursor_rect {
+    if let Some(cr) = hover_rect.as_ref().or(cursor_rect.as_ref()) {
         fg.selection.name = cr.name.clone();
         fg.selection.self_value = cr.self_value;
         fg.selection.total_value = cr.total_value;
@@ -417,10 +869,12 @@ fn render_flamegraph(fg: &mut FlamegraphTab, frame: &mut Frame, area: Rect) {
         } else {
             0.0
         };
+        fg.selection.baseline_pct = cr.baseline_value.map(|b| share(b, baseline_total) * 100.0);
         fg.selection.depth = cr.depth;
+        fg.selection.folded_depth = folded_depth;
     }
 
-    for fr in &frames {
+    for (fr, delta) in frames.iter().zip(&deltas) {
         if fr.depth < fg.scroll_y {
             continue;
         }
@@ -438,82 +892,29 @@ fn render_flamegraph(fg: &mut FlamegraphTab, frame: &mut Frame, area: Rect) {
             .as_ref()
             .is_some_and(|cr| cr.depth == fr.depth && cr.x == fr.x);
 
-        let heat = if fr.total_value > 0 {
-            fr.self_value as f64 / fr.total_value as f64
-        } else {
-            0.0
+        let base_color = match delta {
+            _ if fg
+                .highlight
+                .as_ref()
+                .is_some_and(|h| h.is_match(&fr.name, fg.demangle)) =>
+            {
+                HIGHLIGHT
+            }
+            Some(d) => diff_color(*d, max_delta),
+            None => frame_color(fr, fg.colors, fg.demangle),
         };
-
         let bg = if is_cursor {
-            lighten(flame_color(&fr.name, heat, fr.palette_index), 45)
+            lighten(base_color, 45)
         } else {
-            flame_color(&fr.name, heat, fr.palette_index)
+            base_color
         };
-        let fg_color = contrast_fg(bg);
-
-        let x_start = area.x + fr.x;
-        let x_end = (area.x + fr.x + fr.width).min(area.x + area.width);
-        let border_color = darken(bg, 55);
-
-        for x in x_start..x_end {
-            if let Some(cell) = buf.cell_mut((x, screen_y)) {
-                if x == x_start || x == x_end.saturating_sub(1) {
-                    cell.set_char('▏');
-                    cell.set_style(Style::default().fg(border_color).bg(bg));
-                } else {
-                    cell.set_char(' ');
-                    cell.set_style(Style::default().bg(bg));
-                }
-            }
-        }
-
-        let inner_width = fr.width.saturating_sub(2);
-        if inner_width >= 3 {
-            let max_chars = inner_width as usize;
-            let name = truncate(&fr.name, max_chars);
-            let pad = (inner_width as usize).saturating_sub(name.len()) / 2;
-            let name_x = area.x + fr.x + 1 + pad as u16;
-
-            let style = if is_cursor {
-                Style::default()
-                    .fg(fg_color)
-                    .bg(bg)
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default().fg(fg_color).bg(bg)
-            };
-            buf.set_string(name_x, screen_y, &name, style);
-        }
-
-        if fr.width >= 14 && root_total > 0 {
-            let pct = fr.total_value as f64 / root_total as f64 * 100.0;
-            if pct >= 0.1 {
-                let pct_str = format!("{:.1}%", pct);
-                let pct_x = area.x + fr.x + fr.width - pct_str.len() as u16 - 2;
-                if pct_x > area.x + fr.x + 2 {
-                    let dim_fg = blend(fg_color, bg, 0.45);
-                    buf.set_string(
-                        pct_x,
-                        screen_y,
-                        &pct_str,
-                        Style::default().fg(dim_fg).bg(bg),
-                    );
-                }
-            }
-        }
-
-        if is_cursor
-            && fr.width >= 3
-            && let Some(cell) = buf.cell_mut((area.x + fr.x + 1, screen_y))
-        {
-            cell.set_char('▸');
-            cell.set_style(
-                Style::default()
-                    .fg(Color::White)
-                    .bg(bg)
-                    .add_modifier(Modifier::BOLD),
-            );
-        }
+        let row = Rect {
+            y: screen_y,
+            height: 1,
+            ..area
+        };
+        let label = demangle::label(&fr.name, fg.demangle);
+        draw_frame(buf, row, fr, &label, bg, root_total, is_cursor);
     }
 
     for vis_d in 0..viewport_height {
@@ -534,6 +935,187 @@ fn render_flamegraph(fg: &mut FlamegraphTab, frame: &mut Frame, area: Rect) {
     }
 }
 
+/// Callers above the function, growing upwards, and callees below it, each
+/// half scrolled past `scroll` levels next to the function.
+fn render_butterfly(
+    b: &Butterfly,
+    scroll: [usize; 2],
+    colors: ColorScheme,
+    mode: Demangle,
+    frame: &mut Frame,
+    area: Rect,
+) {
+    let buf = frame.buffer_mut();
+
+    if area.width < 4 || area.height < 4 {
+        return;
+    }
+    if b.callers.total_value <= 0 {
+        render_empty_fg(buf, area);
+        return;
+    }
+
+    let callers_height = area.height / 2;
+    let callers_area = Rect {
+        height: callers_height,
+        ..area
+    };
+    let callees_area = Rect {
+        y: area.y + callers_height,
+        height: area.height - callers_height,
+        ..area
+    };
+    render_butterfly_half(buf, &b.callers, callers_area, true, scroll[0], colors, mode);
+    render_butterfly_half(
+        buf,
+        &b.callees,
+        callees_area,
+        false,
+        scroll[1],
+        colors,
+        mode,
+    );
+}
+
+/// Draws the function on the row nearest the other half and the levels
+/// after the `skip` next to it, noting the levels left out at either end.
+fn render_butterfly_half(
+    buf: &mut Buffer,
+    node: &FlameNode,
+    area: Rect,
+    upwards: bool,
+    skip: usize,
+    colors: ColorScheme,
+    mode: Demangle,
+) {
+    let max_depth = node.max_depth();
+    let skip = skip.min(max_depth.saturating_sub(1));
+    let rows = area.height as usize;
+    let screen_y = |row: usize| {
+        if upwards {
+            area.y + area.height - 1 - row as u16
+        } else {
+            area.y + row as u16
+        }
+    };
+    for fr in layout_frames(node, None, area.width, Some(0)) {
+        let row = match fr.depth {
+            0 => 0,
+            depth if depth > skip => depth - skip,
+            _ => continue,
+        };
+        if row >= rows {
+            continue;
+        }
+        let screen_y = screen_y(row);
+        let bg = if fr.depth == 0 {
+            HIGHLIGHT
+        } else {
+            frame_color(&fr, colors, mode)
+        };
+        let row = Rect {
+            y: screen_y,
+            height: 1,
+            ..area
+        };
+        let label = demangle::label(&fr.name, mode);
+        draw_frame(buf, row, &fr, &label, bg, node.total_value, false);
+    }
+
+    let (near, far) = if upwards {
+        ('▼', '▲')
+    } else {
+        ('▲', '▼')
+    };
+    let style = Style::default().fg(BRIGHT).bg(DIM);
+    if skip > 0 && rows > 1 {
+        let text = format!(" {near} {skip} levels scrolled past ");
+        buf.set_string(area.x, screen_y(1), text, style);
+    }
+    let hidden = max_depth.saturating_sub(skip + rows.saturating_sub(1));
+    if hidden > 0 {
+        let text = format!(" {far} {hidden} more levels ");
+        let x = area
+            .right()
+            .saturating_sub(text.chars().count() as u16)
+            .max(area.x);
+        buf.set_string(x, screen_y(rows - 1), text, style);
+    }
+}
+
+/// Draws one frame into the single-line `row`: border, centered `label`,
+/// share of `root_total` and the cursor marker.
+fn draw_frame(
+    buf: &mut Buffer,
+    row: Rect,
+    fr: &FrameRect,
+    label: &str,
+    bg: Color,
+    root_total: i64,
+    is_cursor: bool,
+) {
+    let fg_color = contrast_fg(bg);
+
+    let x_start = row.x + fr.x;
+    let x_end = (row.x + fr.x + fr.width).min(row.x + row.width);
+    let border_color = darken(bg, 55);
+
+    for x in x_start..x_end {
+        if let Some(cell) = buf.cell_mut((x, row.y)) {
+            if x == x_start || x == x_end.saturating_sub(1) {
+                cell.set_char('▏');
+                cell.set_style(Style::default().fg(border_color).bg(bg));
+            } else {
+                cell.set_char(' ');
+                cell.set_style(Style::default().bg(bg));
+            }
+        }
+    }
+
+    let inner_width = fr.width.saturating_sub(2);
+    if inner_width >= 3 {
+        let max_chars = inner_width as usize;
+        let name = truncate(label, max_chars);
+        let pad = (inner_width as usize).saturating_sub(name.len()) / 2;
+        let name_x = row.x + fr.x + 1 + pad as u16;
+
+        let style = if is_cursor {
+            Style::default()
+                .fg(fg_color)
+                .bg(bg)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(fg_color).bg(bg)
+        };
+        buf.set_string(name_x, row.y, &name, style);
+    }
+
+    if fr.width >= 14 && root_total > 0 {
+        let pct = fr.total_value as f64 / root_total as f64 * 100.0;
+        if pct >= 0.1 {
+            let pct_str = format!("{:.1}%", pct);
+            let pct_x = row.x + fr.x + fr.width - pct_str.len() as u16 - 2;
+            if pct_x > row.x + fr.x + 2 {
+                let dim_fg = blend(fg_color, bg, 0.45);
+                buf.set_string(pct_x, row.y, &pct_str, Style::default().fg(dim_fg).bg(bg));
+            }
+        }
+    }
+
+    if is_cursor
+        && fr.width >= 3
+        && let Some(cell) = buf.cell_mut((row.x + fr.x + 1, row.y))
+    {
+        cell.set_char('▸');
+        cell.set_style(
+            Style::default()
+                .fg(Color::White)
+                .bg(bg)
+                .add_modifier(Modifier::BOLD),
+        );
+    }
+}
+
 fn render_empty_fg(buf: &mut Buffer, area: Rect) {
     let msg = "No profile data yet";
     let y = area.y + area.height / 2;
@@ -557,33 +1139,89 @@ const HEATMAP_STOPS: &[(f64, (u8, u8, u8))] = &[
     (1.00, (252, 255, 164)),
 ];
 
-fn heatmap_color(value: u64, max: u64) -> Color {
-    let t = ((value as f64).sqrt() / (max as f64).sqrt()).clamp(0.0, 1.0);
+/// Heatmap color at `t`, from 0 (coldest) to 1 (hottest).
+fn heatmap_color(t: f64) -> Color {
     let (r, g, b) = gradient(t, HEATMAP_STOPS);
     Color::Rgb(r, g, b)
 }
 
-fn render_flamescope_detail_bar(fs: &FlamescopeTab, frame: &mut Frame, area: Rect) {
+fn render_flamescope_detail_bar(fs: &mut FlamescopeTab, frame: &mut Frame, area: Rect) {
     if fs.visible_columns().is_empty() {
         return;
     }
 
-    let (sec, ms_start, ms_end) = fs.selected_time();
+    let (start, end) = fs.selected_time();
     let value = fs.selected_value();
     let peak = fs.visible_peak();
     let sep = " │ ".fg(Color::Rgb(55, 55, 65));
 
     let mut spans: Vec<Span> = Vec::new();
 
+    if fs.is_approximated() {
+        spans.push(" ≈ approximated ".fg(Color::Rgb(234, 179, 8)).bold());
+        spans.push(sep.clone());
+    }
+
+    let (pos, resources) = fs.resource_position();
+    if let Some(ref resource) = fs.resource
+        && resources > 1
+    {
+        spans.push(
+            format!(" {resource} ({}/{resources}) ", pos + 1)
+                .fg(BRIGHT)
+                .bold(),
+        );
+        spans.push(sep.clone());
+    }
+
     if let Some(ref filter) = fs.filter {
         spans.push(format!(" filtered: {filter} ").fg(ACCENT).bold());
         spans.push(sep.clone());
     }
 
+    if let Some(ref baseline) = fs.baseline {
+        spans.push(
+            format!(
+                " baseline: {} ({} samples) ",
+                baseline.range.label,
+                format_count(baseline.range.samples)
+            )
+            .fg(Color::Rgb(148, 163, 184))
+            .bold(),
+        );
+        spans.push(sep.clone());
+    }
+
+    let markers: Vec<&str> = fs
+        .column_markers(fs.cursor_col)
+        .map(|m| m.label.as_str())
+        .collect();
+    if !markers.is_empty() {
+        spans.push(format!(" ◆ {} ", markers.join(", ")).fg(MARKER).bold());
+        spans.push(sep.clone());
+    }
+
+    if let Some((start, end)) = fs.selection_span() {
+        spans.push(
+            format!(
+                " selected: {} ({} samples) ",
+                format_span(start, end),
+                format_count(fs.selection_value())
+            )
+            .fg(HIGHLIGHT)
+            .bold(),
+        );
+        spans.push(sep.clone());
+    }
+
+    let retention = match fs.retention_secs {
+        Some(secs) => format!("max {}", format_secs(secs)),
+        None => "unbounded".to_string(),
+    };
     spans.extend([
         " ▸ ".fg(ACCENT).bold(),
         Span::styled(
-            format!("{sec}s + {ms_start}\u{2013}{ms_end}ms"),
+            format_span(start, end),
             Style::default().fg(BRIGHT).add_modifier(Modifier::BOLD),
         ),
         sep.clone(),
@@ -592,20 +1230,36 @@ fn render_flamescope_detail_bar(fs: &FlamescopeTab, frame: &mut Frame, area: Rec
         sep.clone(),
         "peak: ".fg(DIM),
         format!("{peak}").fg(Color::Rgb(234, 179, 8)),
-        sep,
-        "duration: ".fg(DIM),
-        format!("{}s", fs.total_seconds()).fg(Color::Rgb(130, 130, 150)),
+        sep.clone(),
+        "retained: ".fg(DIM),
+        format!(
+            "{}s from +{}s ({})",
+            fs.total_seconds(),
+            fs.cell_span((0, 0)).0 / 1_000_000_000,
+            retention
+        )
+        .fg(Color::Rgb(130, 130, 150)),
+        sep.clone(),
+        "resolution: ".fg(DIM),
+        format!("{} rows × {}s", fs.rows, fs.secs_per_col).fg(Color::Rgb(130, 130, 150)),
     ]);
 
     frame.render_widget(Paragraph::new(Line::from(spans)), area);
 }
 
 fn render_flamescope(fs: &mut FlamescopeTab, frame: &mut Frame, area: Rect) {
-    let Some(lay) = FlamescopeLayout::new(area) else {
+    // The bottom line is left for the time axis.
+    let area = Rect {
+        height: area.height.saturating_sub(1),
+        ..area
+    };
+    fs.grid_area = area;
+    let Some(lay) = FlamescopeLayout::new(area, fs.rows) else {
         return;
     };
 
     let total_cols = fs.visible_columns().len();
+    let color_max = fs.color_max();
     let peak = fs.visible_peak();
 
     if total_cols > 0 {
@@ -620,11 +1274,33 @@ fn render_flamescope(fs: &mut FlamescopeTab, frame: &mut Frame, area: Rect) {
     if fs.cursor_col >= fs.scroll_x + lay.visible_cols {
         fs.scroll_x = fs.cursor_col + 1 - lay.visible_cols;
     }
+    fs.cursor_row = fs.cursor_row.min(fs.rows - 1);
+    if fs.cursor_row < fs.scroll_y {
+        fs.scroll_y = fs.cursor_row;
+    }
+    if fs.cursor_row >= fs.scroll_y + lay.visible_rows {
+        fs.scroll_y = fs.cursor_row + 1 - lay.visible_rows;
+    }
+    fs.scroll_y = fs.scroll_y.min(fs.rows - lay.visible_rows);
 
     let vis_data = fs.visible_columns();
     let cursor_col = fs.cursor_col;
     let cursor_row = fs.cursor_row;
     let scroll_x = fs.scroll_x;
+    let scroll_y = fs.scroll_y;
+    let selection = fs.selection();
+    let row_ms: Vec<u64> = (0..fs.rows)
+        .map(|row| row as u64 * fs.row_ns() / 1_000_000)
+        .collect();
+    let axis_labels: Vec<(usize, u64)> = (0..lay.visible_cols)
+        .filter_map(|col_off| Some((col_off, fs.column_unix_secs(scroll_x + col_off)?)))
+        .collect();
+    let secs_per_col = fs.secs_per_col;
+    let marker_cells: Vec<(usize, usize)> = fs
+        .markers
+        .iter()
+        .filter_map(|m| fs.marker_cell(m))
+        .collect();
     let buf = frame.buffer_mut();
 
     if vis_data.is_empty() {
@@ -644,8 +1320,9 @@ fn render_flamescope(fs: &mut FlamescopeTab, frame: &mut Frame, area: Rect) {
         return;
     }
 
-    for row in 0..lay.num_rows() {
-        let y_start = lay.row_y(row);
+    for row_off in 0..lay.visible_rows {
+        let row = scroll_y + row_off;
+        let y_start = lay.row_y(row_off);
         if y_start >= lay.bottom() {
             break;
         }
@@ -655,7 +1332,7 @@ fn render_flamescope(fs: &mut FlamescopeTab, frame: &mut Frame, area: Rect) {
             buf.set_string(
                 lay.label_x(),
                 label_y,
-                format!("{:>3}ms ", lay.ms_label(row)),
+                format!("{:>4}ms ", row_ms[row]),
                 Style::default().fg(DIM),
             );
         }
@@ -664,19 +1341,39 @@ fn render_flamescope(fs: &mut FlamescopeTab, frame: &mut Frame, area: Rect) {
             let col = scroll_x + col_off;
             let value = vis_data.get(col).map_or(0, |c| c[row]);
             let is_cursor = col == cursor_col && row == cursor_row;
+            let is_selected =
+                selection.is_some_and(|(start, end)| (start..=end).contains(&(col, row)));
+            let is_baseline = fs.in_baseline((col, row));
 
-            if value == 0 && !is_cursor {
+            if value == 0 && !is_cursor && !is_selected && !is_baseline {
                 continue;
             }
 
-            let bg = if value > 0 && peak > 0 {
-                let base = heatmap_color(value, peak);
-                if is_cursor { lighten(base, 50) } else { base }
+            let bg = if value > 0 && color_max > 0 {
+                let base = heatmap_color(fs.scale.fraction(value, color_max));
+                if is_cursor {
+                    lighten(base, 50)
+                } else if is_selected {
+                    lighten(base, 25)
+                } else {
+                    base
+                }
             } else if is_cursor {
                 Color::Rgb(40, 40, 55)
+            } else if is_selected {
+                Color::Rgb(30, 30, 42)
+            } else if is_baseline {
+                Color::Rgb(24, 30, 44)
             } else {
                 continue;
             };
+            // Baseline cells are dotted so they stay visible under a new
+            // selection.
+            let (ch, fg) = if is_baseline {
+                ('·', Color::Rgb(148, 163, 184))
+            } else {
+                (' ', Color::Reset)
+            };
 
             let cell_x = lay.cell_x(col_off);
             for dy in 0..lay.cell_h {
@@ -690,13 +1387,404 @@ fn render_flamescope(fs: &mut FlamescopeTab, frame: &mut Frame, area: Rect) {
                         break;
                     }
                     if let Some(cell) = buf.cell_mut((x, y)) {
-                        cell.set_char(' ');
-                        cell.set_style(Style::default().bg(bg));
+                        cell.set_char(ch);
+                        cell.set_style(Style::default().fg(fg).bg(bg));
                     }
                 }
             }
         }
     }
+
+    // Markers are drawn as a line down the left edge of their column, with
+    // a diamond in the cell they fall in.
+    for (col, row) in marker_cells {
+        let Some(col_off) = col
+            .checked_sub(scroll_x)
+            .filter(|&off| off < lay.visible_cols)
+        else {
+            continue;
+        };
+        let x = lay.cell_x(col_off);
+        for row_off in 0..lay.visible_rows {
+            let ch = if scroll_y + row_off == row {
+                '◆'
+            } else {
+                '│'
+            };
+            for y in lay.row_y(row_off)..lay.row_y(row_off + 1).min(lay.bottom()) {
+                if let Some(cell) = buf.cell_mut((x, y)) {
+                    cell.set_char(ch);
+                    cell.set_fg(MARKER);
+                }
+            }
+        }
+    }
+
+    // Wall-clock labels on columns aligned to the label spacing, so they
+    // stay put while scrolling.
+    let label_w = format_clock(0).len() as u16 + 2;
+    let step = label_w.div_ceil(lay.cell_w) as u64;
+    let axis_y = lay.row_y(lay.visible_rows);
+    let legend = heatmap_legend(fs, color_max, peak);
+    let legend_w: u16 = legend
+        .iter()
+        .map(|(text, _)| text.chars().count() as u16)
+        .sum();
+    let legend_x = lay.right().saturating_sub(legend_w);
+    if legend_x >= lay.label_x() {
+        let mut x = legend_x;
+        for (text, color) in &legend {
+            buf.set_string(x, axis_y, text, Style::default().fg(*color));
+            x += text.chars().count() as u16;
+        }
+    }
+    for (col_off, secs) in axis_labels {
+        let x = lay.cell_x(col_off);
+        if (secs / secs_per_col).is_multiple_of(step) && x + label_w <= legend_x {
+            buf.set_string(x, axis_y, format_clock(secs), Style::default().fg(DIM));
+        }
+    }
+}
+
+/// Scale of the heatmap followed by the count at each quarter of it, with
+/// a `+` when hotter cells, up to `peak`, are clipped to the top color.
+fn heatmap_legend(fs: &FlamescopeTab, color_max: u64, peak: u64) -> Vec<(String, Color)> {
+    if color_max == 0 {
+        return Vec::new();
+    }
+    let mut scale = fs.scale.label().to_string();
+    if fs.filter.is_some() {
+        scale.push_str(if fs.global_scale {
+            " · all threads"
+        } else {
+            " · thread"
+        });
+    }
+    let mut legend = vec![(format!("{scale} "), DIM)];
+    let mut previous = 0;
+    for quarter in 1..=4 {
+        let value = fs.scale.value_at(quarter as f64 / 4.0, color_max).max(1);
+        if value == previous {
+            continue;
+        }
+        previous = value;
+        legend.push((
+            "■".to_string(),
+            heatmap_color(fs.scale.fraction(value, color_max)),
+        ));
+        legend.push((format!("{value} "), Color::Rgb(130, 130, 150)));
+    }
+    if peak > color_max
+        && let Some((text, _)) = legend.last_mut()
+    {
+        text.insert(text.len() - 1, '+');
+    }
+    legend
+}
+
+fn render_functions_status_bar(ft: &FunctionsTab, frame: &mut Frame, area: Rect) {
+    let sep = " │ ".fg(Color::Rgb(55, 55, 65));
+    let mut spans: Vec<Span> =
+        vec![format!(" {} functions", ft.visible.len()).fg(Color::Rgb(130, 130, 150))];
+    if ft.filtering || !ft.filter.is_empty() {
+        spans.push(sep);
+        spans.push("filter: ".fg(DIM));
+        let cursor = if ft.filtering { "█" } else { "" };
+        spans.push(format!("{}{cursor}", ft.filter).fg(BRIGHT));
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn render_functions_table(ft: &mut FunctionsTab, mode: Demangle, frame: &mut Frame, area: Rect) {
+    let buf = frame.buffer_mut();
+
+    if area.height < 2 {
+        return;
+    }
+
+    const NUM_W: u16 = 16;
+    const TYPE_W: u16 = 10;
+    let col_self = area.x + 1;
+    let col_total = col_self + NUM_W;
+    let col_type = col_total + NUM_W;
+    let col_name = col_type + TYPE_W;
+    let name_w = (area.x + area.width).saturating_sub(col_name) as usize;
+
+    let hdr_style = Style::default().fg(DIM).add_modifier(Modifier::BOLD);
+    let sorted_style = Style::default().fg(ACCENT).add_modifier(Modifier::BOLD);
+    for (x, label, column) in [
+        (col_self, "Self", Some(SortColumn::SelfSamples)),
+        (col_total, "Total", Some(SortColumn::TotalSamples)),
+        (col_type, "Type", None),
+        (col_name, "Function", Some(SortColumn::Name)),
+    ] {
+        let (text, style) = if column == Some(ft.sort) {
+            (format!("{label} ▼"), sorted_style)
+        } else {
+            (label.to_string(), hdr_style)
+        };
+        buf.set_string(x, area.y, text, style);
+    }
+
+    let sep_y = area.y + 1;
+    for x in area.x..area.x + area.width {
+        if let Some(c) = buf.cell_mut((x, sep_y)) {
+            c.set_char('─');
+            c.set_style(Style::default().fg(SEP_COLOR));
+        }
+    }
+
+    let visible_rows = (area.y + area.height).saturating_sub(sep_y + 1) as usize;
+    if visible_rows == 0 {
+        return;
+    }
+    if ft.cursor < ft.scroll {
+        ft.scroll = ft.cursor;
+    }
+    if ft.cursor >= ft.scroll + visible_rows {
+        ft.scroll = ft.cursor + 1 - visible_rows;
+    }
+
+    let cursor_bg = Color::Rgb(40, 45, 65);
+    let pct = |v: i64| {
+        if ft.root_total > 0 {
+            v as f64 / ft.root_total as f64 * 100.0
+        } else {
+            0.0
+        }
+    };
+
+    for vis_row in 0..visible_rows {
+        let idx = ft.scroll + vis_row;
+        let Some(row) = ft.row(idx) else {
+            break;
+        };
+        let y = sep_y + 1 + vis_row as u16;
+        let is_cursor = idx == ft.cursor;
+        let row_bg = if is_cursor { cursor_bg } else { Color::Reset };
+
+        if is_cursor {
+            for x in area.x..area.x + area.width {
+                if let Some(c) = buf.cell_mut((x, y)) {
+                    c.set_char(' ');
+                    c.set_style(Style::default().bg(cursor_bg));
+                }
+            }
+        }
+
+        buf.set_string(
+            col_self,
+            y,
+            format!(
+                "{:>6} {:>5.1}%",
+                format_count(row.self_value as u64),
+                pct(row.self_value)
+            ),
+            Style::default().fg(Color::Rgb(249, 115, 22)).bg(row_bg),
+        );
+        buf.set_string(
+            col_total,
+            y,
+            format!(
+                "{:>6} {:>5.1}%",
+                format_count(row.total_value as u64),
+                pct(row.total_value)
+            ),
+            Style::default().fg(Color::Rgb(234, 179, 8)).bg(row_bg),
+        );
+        buf.set_string(
+            col_type,
+            y,
+            row.frame_type.label(),
+            Style::default().fg(Color::Rgb(130, 130, 150)).bg(row_bg),
+        );
+        let name_style = if is_cursor {
+            Style::default()
+                .fg(BRIGHT)
+                .bg(row_bg)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Rgb(180, 180, 195)).bg(row_bg)
+        };
+        buf.set_string(
+            col_name,
+            y,
+            truncate(&demangle::label(&row.name, mode), name_w),
+            name_style,
+        );
+    }
+
+    if ft.visible.is_empty() {
+        let msg = if ft.filter.is_empty() {
+            "No profile data yet"
+        } else {
+            "No functions match the filter"
+        };
+        let y = sep_y + 2;
+        if y < area.y + area.height {
+            buf.set_string(
+                area.x + 2,
+                y,
+                msg,
+                Style::default().fg(DIM).add_modifier(Modifier::ITALIC),
+            );
+        }
+    }
+}
+
+fn render_threads_status_bar(tt: &ThreadsTab, frame: &mut Frame, area: Rect) {
+    let sep = " │ ".fg(Color::Rgb(55, 55, 65));
+    let mut spans: Vec<Span> = vec![
+        format!(
+            " {} {}",
+            tt.rows.len(),
+            if tt.grouped { "groups" } else { "threads" }
+        )
+        .fg(Color::Rgb(130, 130, 150)),
+        sep.clone(),
+        "history: ".fg(DIM),
+        format!("{}s", tt.history_secs()).fg(Color::Rgb(130, 130, 150)),
+    ];
+    if let Some(row) = tt.rows.get(tt.cursor) {
+        spans.push(sep);
+        spans.push(" ▸ ".fg(ACCENT).bold());
+        spans.push(row.name.clone().fg(BRIGHT).bold());
+        if row.members > 1 {
+            spans.push(
+                format!(" ({} threads, busiest {})", row.members, row.thread)
+                    .fg(Color::Rgb(130, 130, 150)),
+            );
+        }
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// One sparkline of samples per second per thread, all on the same scale
+/// so that the busiest threads stand out.
+fn render_threads(tt: &mut ThreadsTab, frame: &mut Frame, area: Rect) {
+    const NAME_W: u16 = 28;
+    const RECENT_W: u16 = 12;
+    const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let buf = frame.buffer_mut();
+    if area.height < 3 || area.width < NAME_W + RECENT_W + 10 {
+        return;
+    }
+
+    let col_name = area.x + 1;
+    let col_spark = col_name + NAME_W;
+    let spark_w = (area.width - NAME_W - RECENT_W - 2) as usize;
+    let col_recent = col_spark + spark_w as u16 + 1;
+
+    let hdr_style = Style::default().fg(DIM).add_modifier(Modifier::BOLD);
+    buf.set_string(col_name, area.y, "Thread", hdr_style);
+    buf.set_string(
+        col_spark,
+        area.y,
+        format!(
+            "Samples per second, last {}s",
+            spark_w.min(tt.history_secs())
+        ),
+        hdr_style,
+    );
+    let recent_hdr = format!("Last {RECENT_SECS}s");
+    buf.set_string(
+        col_recent,
+        area.y,
+        format!("{recent_hdr:>w$}", w = RECENT_W as usize - 1),
+        hdr_style,
+    );
+
+    let sep_y = area.y + 1;
+    for x in area.x..area.x + area.width {
+        if let Some(c) = buf.cell_mut((x, sep_y)) {
+            c.set_char('─');
+            c.set_style(Style::default().fg(SEP_COLOR));
+        }
+    }
+
+    if tt.rows.is_empty() {
+        let msg = "No samples yet";
+        let x = area.x + (area.width.saturating_sub(msg.len() as u16)) / 2;
+        buf.set_string(
+            x,
+            sep_y + 2,
+            msg,
+            Style::default().fg(DIM).add_modifier(Modifier::ITALIC),
+        );
+        return;
+    }
+
+    let visible_rows = (area.y + area.height).saturating_sub(sep_y + 1) as usize;
+    if tt.cursor < tt.scroll {
+        tt.scroll = tt.cursor;
+    }
+    if tt.cursor >= tt.scroll + visible_rows {
+        tt.scroll = tt.cursor + 1 - visible_rows;
+    }
+
+    let tail = |rates: &[u64]| rates.len().saturating_sub(spark_w);
+    let peak = tt
+        .rows
+        .iter()
+        .flat_map(|row| &row.rates[tail(&row.rates)..])
+        .copied()
+        .max()
+        .unwrap_or(0);
+    let cursor_bg = Color::Rgb(40, 45, 65);
+
+    for (vis_row, idx) in (tt.scroll..tt.rows.len()).take(visible_rows).enumerate() {
+        let row = &tt.rows[idx];
+        let y = sep_y + 1 + vis_row as u16;
+        let row_bg = if idx == tt.cursor {
+            cursor_bg
+        } else {
+            Color::Reset
+        };
+        if idx == tt.cursor {
+            for x in area.x..area.x + area.width {
+                if let Some(c) = buf.cell_mut((x, y)) {
+                    c.set_char(' ');
+                    c.set_style(Style::default().bg(cursor_bg));
+                }
+            }
+        }
+
+        buf.set_string(
+            col_name,
+            y,
+            truncate(&row.name, NAME_W as usize - 2),
+            Style::default().fg(BRIGHT).bg(row_bg),
+        );
+
+        let rates = &row.rates[tail(&row.rates)..];
+        let x0 = col_spark + (spark_w - rates.len()) as u16;
+        for (i, &rate) in rates.iter().enumerate() {
+            if rate == 0 || peak == 0 {
+                continue;
+            }
+            let level = ((rate * 8).div_ceil(peak) as usize).clamp(1, 8);
+            if let Some(c) = buf.cell_mut((x0 + i as u16, y)) {
+                c.set_char(BLOCKS[level]);
+                c.set_style(
+                    Style::default()
+                        .fg(heatmap_color(HeatmapScale::Sqrt.fraction(rate, peak)))
+                        .bg(row_bg),
+                );
+            }
+        }
+
+        buf.set_string(
+            col_recent,
+            y,
+            format!(
+                "{:>w$}",
+                format_count(row.recent),
+                w = RECENT_W as usize - 1
+            ),
+            Style::default().fg(Color::Rgb(249, 115, 22)).bg(row_bg),
+        );
+    }
 }
 
 fn render_exe_status_bar(status: Option<&str>, frame: &mut Frame, area: Rect) {
@@ -1045,16 +2133,69 @@ const FLAMEGRAPH_KEYS: &[(&str, &str)] = &[
     ("[h/← l/→]", " frame "),
     ("[Enter]", " zoom "),
     ("[Esc]", " back "),
+    ("[t]", " threads "),
     ("[/]", " search "),
+    ("[n/N]", " next match "),
+    ("[c]", " callers/callees "),
+    ("[F/I/H]", " focus/ignore/hide "),
+    ("[T]", " frame types "),
+    ("[1-9/X]", " toggle/clear filters "),
+    ("[i]", " invert "),
+    ("[R]", " fold recursion "),
+    ("[M]", " demangle "),
+    ("[m]", " mouse "),
+    ("[C]", " colors "),
+    ("[b/B]", " baseline "),
+    ("[s/S]", " snapshot "),
+    ("[w/W]", " window "),
     ("[r]", " reset "),
 ];
 
+const BUTTERFLY_KEYS: &[(&str, &str)] = &[
+    ("[Tab]", " switch "),
+    ("[q]", " quit "),
+    ("[f/Space]", " freeze "),
+    ("[Esc/c]", " back to graph "),
+    ("[k/j]", " scroll callers "),
+    ("[J/K]", " scroll callees "),
+    ("[C]", " colors "),
+];
+
+const SNAPSHOT_LIST_KEYS: &[(&str, &str)] = &[
+    ("[Esc]", " cancel "),
+    ("[Enter]", " view "),
+    ("[↑↓]", " navigate "),
+    ("[Del]", " delete "),
+];
+
 const SEARCH_KEYS: &[(&str, &str)] = &[
     ("[Esc]", " cancel "),
     ("[Enter]", " select "),
     ("[↑↓]", " navigate "),
 ];
 
+const FUNCTIONS_KEYS: &[(&str, &str)] = &[
+    ("[Tab]", " switch "),
+    ("[q]", " quit "),
+    ("[j/k]", " navigate "),
+    ("[s]", " sort "),
+    ("[M]", " demangle "),
+    ("[/]", " filter "),
+    ("[Esc]", " clear filter "),
+    ("[Enter]", " show in flamegraph "),
+];
+
+const THREADS_KEYS: &[(&str, &str)] = &[
+    ("[Tab]", " switch "),
+    ("[q]", " quit "),
+    ("[j/k]", " navigate "),
+    ("[g]", " group "),
+    ("[Enter]", " flamegraph "),
+    ("[f]", " flamescope "),
+];
+
+const FILTER_KEYS: &[(&str, &str)] = &[("[Esc]", " clear "), ("[Enter]", " apply ")];
+
 const FLAMESCOPE_KEYS: &[(&str, &str)] = &[
     ("[Tab]", " switch "),
     ("[q]", " quit "),
@@ -1062,7 +2203,18 @@ const FLAMESCOPE_KEYS: &[(&str, &str)] = &[
     ("[j/↓ k/↑]", " offset "),
     ("[/]", " filter "),
     ("[Esc]", " unfilter "),
+    ("[Space]", " mark start/end "),
+    ("[Enter]", " flamegraph of range "),
+    ("[b/B]", " baseline "),
+    ("[c]", " color scale "),
+    ("[g]", " thread/global colors "),
+    ("[a]", " marker "),
+    ("[n/N]", " next/prev marker "),
+    ("[+/-]", " rows "),
+    ("[[/]]", " secs/col "),
+    ("[R]", " resource "),
     ("[G]", " latest "),
+    ("[m]", " mouse "),
     ("[r]", " reset "),
 ];
 
@@ -1193,6 +2345,109 @@ fn flame_color(name: &str, heat: f64, palette_index: usize) -> Color {
     )
 }
 
+/// Base colors of the frame type scheme, indexed by `FrameType as usize`.
+const TYPE_COLORS: [(u8, u8, u8); FrameType::ALL.len()] = [
+    (148, 163, 184),
+    (249, 115, 22),
+    (220, 38, 38),
+    (34, 197, 94),
+    (59, 130, 246),
+    (139, 92, 246),
+    (225, 29, 72),
+    (132, 204, 22),
+    (234, 179, 8),
+    (168, 85, 247),
+    (20, 184, 166),
+    (0, 173, 216),
+];
+
+/// Color of a frame under `scheme`, darker the larger its share of self
+/// time.
+fn frame_color(fr: &FrameRect, scheme: ColorScheme, mode: Demangle) -> Color {
+    let heat = if fr.total_value > 0 {
+        fr.self_value as f64 / fr.total_value as f64
+    } else {
+        0.0
+    };
+    let base = match scheme {
+        ColorScheme::Thread => return flame_color(&fr.name, heat, fr.palette_index),
+        ColorScheme::Name => hash_color(&fr.name),
+        _ => scheme_entry(scheme, &fr.name, fr.frame_type, &fr.module, mode)
+            .map_or(BRIGHT, |(_, color)| color),
+    };
+    darken(base, (heat.clamp(0.0, 1.0) * 60.0) as u8)
+}
+
+/// Legend entry of a frame and its color, for the schemes that group
+/// frames under a few keys.
+fn scheme_entry(
+    scheme: ColorScheme,
+    name: &str,
+    frame_type: FrameType,
+    module: &str,
+    mode: Demangle,
+) -> Option<(String, Color)> {
+    let (r, g, b) = TYPE_COLORS[frame_type as usize];
+    let type_entry = |key: String| Some((key, Color::Rgb(r, g, b)));
+    match scheme {
+        ColorScheme::Thread | ColorScheme::Name => None,
+        ColorScheme::FrameType => type_entry(frame_type.label().to_string()),
+        ColorScheme::Module if module.is_empty() => type_entry(format!("[{}]", frame_type.label())),
+        ColorScheme::Module => Some((module.to_string(), hash_color(module))),
+        ColorScheme::Package => {
+            let key = package(&demangle::label(name, mode)).to_string();
+            let color = hash_color(&key);
+            Some((key, color))
+        }
+    }
+}
+
+/// Color picked by a fixed (FNV-1a) hash of `key`, so that it stays the
+/// same across sessions and builds.
+fn hash_color(key: &str) -> Color {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    let hue = (hash % 360) as f64;
+    let lightness = 0.5 + ((hash >> 16) % 16) as f64 / 100.0;
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * 0.7;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |v: f64| ((v + lightness - chroma / 2.0) * 255.0).round() as u8;
+    Color::Rgb(channel(r), channel(g), channel(b))
+}
+
+const DIFF_NEUTRAL: (u8, u8, u8) = (215, 215, 225);
+const DIFF_GROWTH: (u8, u8, u8) = (220, 38, 38);
+const DIFF_SHRINK: (u8, u8, u8) = (37, 99, 235);
+
+/// Red for frames whose share grew relative to the baseline, blue for
+/// frames that shrank, scaled by the largest visible change.
+fn diff_color(delta: f64, max_delta: f64) -> Color {
+    let t = if max_delta > 0.0 {
+        (delta.abs() / max_delta).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let target = if delta >= 0.0 {
+        DIFF_GROWTH
+    } else {
+        DIFF_SHRINK
+    };
+    Color::Rgb(
+        lerp_u8(DIFF_NEUTRAL.0, target.0, t),
+        lerp_u8(DIFF_NEUTRAL.1, target.1, t),
+        lerp_u8(DIFF_NEUTRAL.2, target.2, t),
+    )
+}
+
 fn gradient(t: f64, stops: &[(f64, (u8, u8, u8))]) -> (u8, u8, u8) {
     let t = t.clamp(0.0, 1.0);
     for i in 0..stops.len() - 1 {
@@ -1288,6 +2543,14 @@ fn truncate(s: &str, max: usize) -> String {
     }
 }
 
+fn format_secs(secs: u64) -> String {
+    if secs >= 60 && secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
 fn format_count(n: u64) -> String {
     if n >= 1_000_000 {
         format!("{:.1}M", n as f64 / 1_000_000.0)